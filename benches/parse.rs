@@ -0,0 +1,63 @@
+//! Compare the owned parser with the borrowed message view on a typical
+//! response carrying a handful of compressed records
+
+use std::hint::black_box;
+use std::net::Ipv4Addr;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use dns_resolver::message::DnsMessage;
+use dns_resolver::message_ref::DnsMessageRef;
+use dns_resolver::name::Name;
+use dns_resolver::rdata::RData;
+use dns_resolver::rr::ResourceRecord;
+use dns_resolver::types::{Class, RecordType};
+
+fn response() -> Vec<u8> {
+    let name: Name = "www.example.com".parse().unwrap();
+    let mut dns_msg = DnsMessage::new(name.clone());
+    for i in 0..8 {
+        dns_msg.answers.push(ResourceRecord {
+            an_name: name.clone(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, i)),
+        });
+    }
+    for ns in ["a.iana-servers.net", "b.iana-servers.net"] {
+        dns_msg.authorities.push(ResourceRecord {
+            an_name: name.parent().unwrap(),
+            an_type: RecordType::NS,
+            an_class: Class::IN,
+            an_ttl: 86400,
+            an_rdata: RData::Ns(ns.parse().unwrap()),
+        });
+    }
+    dns_msg.header.flags.set_qr(true);
+    dns_msg.to_be_bytes().unwrap()
+}
+
+fn parse(c: &mut Criterion) {
+    let bytes = response();
+
+    c.bench_function("owned: parse", |b| {
+        b.iter(|| DnsMessage::parse(black_box(&bytes)).unwrap())
+    });
+    c.bench_function("borrowed: header and answer ttls", |b| {
+        b.iter(|| {
+            let view = DnsMessageRef::parse(black_box(&bytes)).unwrap();
+            view.answers().map(|rr| rr.unwrap().an_ttl).min()
+        })
+    });
+    c.bench_function("borrowed: walk all sections", |b| {
+        b.iter(|| {
+            let view = DnsMessageRef::parse(black_box(&bytes)).unwrap();
+            let answers = view.answers().filter(Result::is_ok).count();
+            let authorities = view.authorities().filter(Result::is_ok).count();
+            answers + authorities
+        })
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
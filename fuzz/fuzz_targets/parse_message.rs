@@ -0,0 +1,37 @@
+#![no_main]
+
+use dns_resolver::message::DnsMessage;
+use dns_resolver::message_ref::DnsMessageRef;
+use libfuzzer_sys::fuzz_target;
+
+// Parsing must never panic, and whatever parses must survive a round trip
+// through the encoder both with and without name compression
+fuzz_target!(|data: &[u8]| {
+    if let Ok(dns_msg) = DnsMessage::parse(data) {
+        assert_eq!(
+            DnsMessage::parse(&dns_msg.to_be_bytes().unwrap()).as_ref(),
+            Ok(&dns_msg)
+        );
+        assert_eq!(
+            DnsMessage::parse(&dns_msg.encode(false).unwrap()).as_ref(),
+            Ok(&dns_msg)
+        );
+    }
+
+    // Walking the view of the message must never panic either
+    if let Ok(view) = DnsMessageRef::parse(data) {
+        for question in view.questions().flatten() {
+            let _ = question.q_name.to_string();
+        }
+        for record in view
+            .answers()
+            .chain(view.authorities())
+            .chain(view.additionals())
+            .flatten()
+        {
+            let _ = record.an_name.to_string();
+            let _ = record.to_owned();
+        }
+        let _ = view.edns();
+    }
+});
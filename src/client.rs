@@ -1,128 +1,408 @@
 use spdlog::prelude::*;
-use std::net::{Ipv4Addr, UdpSocket};
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use message::DnsMessage;
-use rr::ResourceRecord;
+use crate::edns::{Edns, EdnsOption};
+use crate::error::ResolveError;
+use crate::header::Rcode;
+use crate::message::DnsMessage;
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::types::{Class, RecordType};
+use config::ResolverConfig;
+use lookup::Lookup;
+use transport::{Server, Transport};
+use udp::UdpTransport;
 
-pub mod header;
-pub mod message;
-pub mod question;
-pub mod rr;
-pub mod utility;
+pub mod config;
+#[cfg(feature = "https")]
+pub mod https;
+pub mod lookup;
+#[cfg(feature = "quic")]
+pub mod quic;
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transport;
+pub mod udp;
 
-/// A DNS client to query for a host name
-pub struct DnsClient {
-    binding_socket: UdpSocket,
+/// Block size queries are padded to (RFC 8467)
+const QUERY_PADDING_BLOCK_SIZE: NonZeroU16 = NonZeroU16::new(128).unwrap();
+
+/// A DNS client to query for a host name, sending its queries over a
+/// transport, UDP falling back to TCP by default
+pub struct DnsClient<T = UdpTransport> {
+    transport: T,
+    /// EDNS information attached to every query, if any
+    edns: Option<Edns>,
+    /// Whether queries are padded to a multiple of the padding block size
+    padding: bool,
+    /// Whether queries ask the server to resolve them recursively
+    recursion_desired: bool,
+    /// Timeouts and retry policy of lookups
+    config: ResolverConfig,
+}
+
+impl Default for DnsClient {
+    fn default() -> Self {
+        DnsClient::new()
+    }
 }
 
 impl DnsClient {
-    /// Create a new DNS client
+    /// Create a new DNS client sending queries over UDP
     pub fn new() -> DnsClient {
-        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).expect("Can't create socket!");
-
-        debug!(
-            "Initialize host at address: {:#?}",
-            socket.local_addr().unwrap()
-        );
+        let transport = UdpTransport::new().expect("Can't create socket!");
+        DnsClient::with_transport(transport)
+    }
+}
 
+impl<T: Transport> DnsClient<T> {
+    /// Create a new DNS client sending queries over a transport
+    pub fn with_transport(transport: T) -> DnsClient<T> {
         DnsClient {
-            binding_socket: socket,
+            transport,
+            edns: Some(Edns::default()),
+            padding: false,
+            recursion_desired: false,
+            config: ResolverConfig::default(),
         }
     }
 
-    /// Query a host name from a DNS server
-    pub fn ask(&self, host_name: &str, root_dns_server: &str, max_retries: u32) {
-        let dns_question = message::DnsMessage::new(host_name);
-        let mut dns_server = root_dns_server.to_string();
-        let mut retry = 0;
-
-        while retry < max_retries {
-            let conn = self.connect(&dns_server, 53);
-            if conn.is_ok() {
-                info!("Querying {} for {}", dns_server, host_name);
-                self.send(&dns_server, 53, &dns_question.into_bytes());
-                let bytes = self.listen().unwrap();
-                let dns_response = DnsMessage::parse(&bytes).unwrap();
-                debug!(
-                    "qd_cnt = {}, an_cnt = {}, ns_cnt = {}, ar_cnt = {}",
-                    dns_response.header.qd_cnt,
-                    dns_response.header.an_cnt,
-                    dns_response.header.ns_cnt,
-                    dns_response.header.ar_cnt
-                );
-                if dns_response.header.an_cnt > 0 {
-                    info!("IP Address:");
-                    println!(
-                        "[\t{}\n]",
-                        self.parse_rr(0x01, &dns_response.answers).join("\n\t")
-                    );
-                    break;
-                } else if dns_response.header.ar_cnt > 0 {
-                    let auth_servers = self.parse_rr(0x01, &dns_response.additionals);
-                    dns_server = auth_servers[0].clone();
+    /// Transport queries are sent over
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Set the EDNS information attached to queries, or send plain DNS
+    /// queries when `None`
+    pub fn set_edns(&mut self, edns: Option<Edns>) {
+        self.edns = edns;
+    }
+
+    /// Attach an EDNS option such as a client subnet, a cookie or an NSID
+    /// request to every query, enabling EDNS if needed
+    pub fn add_edns_option(&mut self, option: EdnsOption) {
+        self.edns
+            .get_or_insert_with(Edns::default)
+            .options
+            .push(option);
+    }
+
+    /// Pad queries sent with EDNS to a multiple of 128 bytes (RFC 8467)
+    pub fn set_padding(&mut self, padding: bool) {
+        self.padding = padding;
+    }
+
+    /// Ask servers to resolve queries recursively (RD bit), as needed with
+    /// recursive resolvers, rather than to answer them from their own data
+    pub fn set_recursion_desired(&mut self, recursion_desired: bool) {
+        self.recursion_desired = recursion_desired;
+    }
+
+    /// Set the timeouts and retry policy of lookups
+    pub fn set_config(&mut self, config: ResolverConfig) {
+        self.config = config;
+    }
+
+    /// Look a host name up, starting from a DNS server and following the
+    /// referrals it gives, within the limits of the resolver configuration
+    pub fn ask(
+        &self,
+        host_name: &Name,
+        q_type: RecordType,
+        q_class: Class,
+        root_dns_server: &Server,
+    ) -> Result<Lookup, ResolveError> {
+        let mut query = DnsMessage::query(host_name.clone())
+            .qtype(q_type)
+            .class(q_class)
+            .recursion_desired(self.recursion_desired);
+        if let Some(edns) = &self.edns {
+            query = query.edns(edns.clone());
+        }
+        let mut dns_question = query.build();
+        if self.padding {
+            dns_question.pad(QUERY_PADDING_BLOCK_SIZE);
+        }
+        let question = dns_question.questions[0].clone();
+        let mut dns_server = root_dns_server.clone();
+        let mut last_error = ResolveError::NoAnswer;
+        // Failed queries in a row, which the wait before the next one grows with
+        let mut failures = 0;
+        let start = Instant::now();
+
+        for _ in 0..self.config.attempts {
+            if failures > 0 {
+                let backoff = self.config.backoff(failures);
+                let Some(remaining) = self.config.attempt_timeout(start, Instant::now()) else {
+                    return Err(ResolveError::Timeout);
+                };
+                thread::sleep(backoff.min(remaining));
+            }
+            let Some(timeout) = self.config.attempt_timeout(start, Instant::now()) else {
+                return Err(ResolveError::Timeout);
+            };
+
+            info!("Querying {} for {} {}", dns_server, host_name, q_type);
+            let sent = Instant::now();
+            let dns_response = match self.exchange(&dns_server, &dns_question, timeout) {
+                Ok(response) => response,
+                Err(err) => {
+                    warn!("Query to {} failed: {}", dns_server, err);
+                    last_error = err;
+                    failures += 1;
+                    continue;
                 }
+            };
+            let rtt = sent.elapsed();
+
+            let is_referral = dns_response.rcode() == Rcode::NoError
+                && dns_response.answers.is_empty()
+                && !dns_response.header.flags.aa()
+                && !name_servers(&dns_response).is_empty();
+            if is_referral {
+                let Some((name_server, addr)) = glue(&dns_response) else {
+                    warn!("Referral from {} without glue", dns_server);
+                    return Err(ResolveError::NoAnswer);
+                };
+                // Encrypted transports check the certificate of the name
+                // server against its name rather than its glue address
+                let host = name_server.to_string();
+                dns_server = Server::at(host.trim_end_matches('.'), addr);
+                last_error = ResolveError::NoAnswer;
+                failures = 0;
+                continue;
+            }
+            match Lookup::from_response(
+                question.clone(),
+                dns_response,
+                dns_server.clone(),
+                start.elapsed(),
+                rtt,
+            ) {
+                // Another attempt may reach a server in a better state
+                Err(ResolveError::ServFail) => {
+                    last_error = ResolveError::ServFail;
+                    failures += 1;
+                }
+                result => return result,
             }
-            retry += 1;
         }
+        Err(last_error)
     }
 
-    /// Parse resource records and transform them into ip addresses
-    fn parse_rr(&self, rr_type: u16, rrs: &Vec<ResourceRecord>) -> Vec<String> {
-        let mut ip_addrs = vec![];
-        for i in 0..rrs.len() {
-            if rrs[i].an_type == rr_type {
-                ip_addrs.push(self.get_ip_addr(&rrs[i]));
-            }
-        }
-        ip_addrs
-    }
-
-    /// Show IP address in DNS answer section
-    fn get_ip_addr(&self, rr: &ResourceRecord) -> String {
-        rr.an_rdata
-            .iter()
-            .map(|&seg| seg.to_string())
-            .collect::<Vec<String>>()
-            .join(".")
-    }
-
-    /// Send a udp message to a remote address
-    fn send(&self, remote_addr: &str, port: u16, msg: &[u8]) -> usize {
-        let result: usize = 0;
-        let addr = format!("{}:{}", remote_addr, port);
-        match self.binding_socket.send_to(msg, addr) {
-            Ok(number_of_bytes) => {
-                debug!(
-                    "Send a {}-byte message to address: {}:{}",
-                    number_of_bytes, remote_addr, port
-                );
+    /// Send a query to a DNS server over the transport and wait at most
+    /// `timeout` for its response
+    fn exchange(
+        &self,
+        dns_server: &Server,
+        query: &DnsMessage,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let dns_response = self.transport.exchange(query, dns_server, timeout)?;
+        if let Some(edns) = &dns_response.edns {
+            for option in &edns.options {
+                info!("{}", option);
             }
-            Err(_) => error!(
-                "Failed sending message: {}",
-                std::str::from_utf8(msg).unwrap().to_string()
-            ),
         }
+        debug!(
+            "qd_cnt = {}, an_cnt = {}, ns_cnt = {}, ar_cnt = {}",
+            dns_response.header.qd_cnt,
+            dns_response.header.an_cnt,
+            dns_response.header.ns_cnt,
+            dns_response.header.ar_cnt
+        );
+        Ok(dns_response)
+    }
+}
+
+/// Name servers a referral delegates to
+fn name_servers(referral: &DnsMessage) -> Vec<&Name> {
+    referral
+        .authorities
+        .iter()
+        .filter_map(|rr| match &rr.an_rdata {
+            RData::Ns(name_server) => Some(name_server),
+            _ => None,
+        })
+        .collect()
+}
 
-        result
+/// Name server a referral delegates to and its address, from the glue in
+/// its additional section, preferring IPv4 addresses
+fn glue(referral: &DnsMessage) -> Option<(&Name, IpAddr)> {
+    let name_servers = name_servers(referral);
+    let mut ipv6 = None;
+    for rr in &referral.additionals {
+        if !name_servers.contains(&&rr.an_name) {
+            continue;
+        }
+        match rr.an_rdata {
+            RData::A(addr) => return Some((&rr.an_name, addr.into())),
+            RData::Aaaa(addr) => ipv6 = ipv6.or(Some((&rr.an_name, addr.into()))),
+            _ => {}
+        }
     }
+    ipv6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rr::ResourceRecord;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use transport::MockTransport;
 
-    /// Connect to a remote address on a port
-    fn connect(&self, remote_addr: &str, port: u16) -> std::io::Result<()> {
-        debug!("Connecting to {}:{}", remote_addr, port);
-        let addr = format!("{}:{}", remote_addr, port);
-        self.binding_socket.connect(addr)
+    fn mock_client() -> DnsClient<MockTransport> {
+        let mut client = DnsClient::with_transport(MockTransport::new());
+        client.set_config(ResolverConfig {
+            attempts: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            ..ResolverConfig::default()
+        });
+        client
     }
 
-    /// Listen to a response from a remote address
-    fn listen(&self) -> Option<Vec<u8>> {
-        let mut buffer = [0; 1024];
-        match self.binding_socket.recv_from(&mut buffer) {
-            Ok((number_of_bytes, _)) => {
-                debug!("Received: {} bytes", number_of_bytes);
-                let result = Vec::from(&buffer[0..number_of_bytes]);
-                Some(result)
-            }
-            Err(_) => None,
+    fn address(owner: &str, addr: Ipv4Addr) -> ResourceRecord {
+        ResourceRecord {
+            an_name: owner.parse().unwrap(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(addr),
         }
     }
+
+    /// Referral of example.com to ns.example.com, without glue
+    fn referral() -> DnsMessage {
+        let mut referral = response();
+        referral.authorities.push(ResourceRecord {
+            an_name: "example.com".parse().unwrap(),
+            an_type: RecordType::NS,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::Ns("ns.example.com".parse().unwrap()),
+        });
+        referral
+    }
+
+    fn response() -> DnsMessage {
+        let mut response = DnsMessage::new("www.example.com".parse().unwrap());
+        response.header.flags.set_qr(true);
+        response
+    }
+
+    fn ask(client: &DnsClient<MockTransport>) -> Result<Lookup, ResolveError> {
+        let name = "www.example.com".parse().unwrap();
+        client.ask(
+            &name,
+            RecordType::A,
+            Class::IN,
+            &Server::new("198.51.100.1"),
+        )
+    }
+
+    fn hosts_queried(client: &DnsClient<MockTransport>) -> Vec<String> {
+        let queries = client.transport().queries();
+        queries
+            .into_iter()
+            .map(|(server, _)| server.addr.map_or(server.host, |addr| addr.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn follow_referral_to_authoritative_server() {
+        let client = mock_client();
+        let mut referral = referral();
+        referral
+            .additionals
+            .push(address("other.example.net", Ipv4Addr::new(192, 0, 2, 99)));
+        referral
+            .additionals
+            .push(address("ns.example.com", Ipv4Addr::new(192, 0, 2, 53)));
+        client.transport().add_response("198.51.100.1", referral);
+        let mut answer = response();
+        answer.header.flags.set_aa(true);
+        answer
+            .answers
+            .push(address("www.example.com", Ipv4Addr::new(192, 0, 2, 1)));
+        client.transport().add_response("192.0.2.53", answer);
+
+        let lookup = ask(&client).unwrap();
+        assert_eq!(
+            lookup.server,
+            Server::at("ns.example.com", Ipv4Addr::new(192, 0, 2, 53).into())
+        );
+        assert!(lookup.authoritative);
+        assert_eq!(
+            lookup.rdata().collect::<Vec<_>>(),
+            [&RData::A(Ipv4Addr::new(192, 0, 2, 1))]
+        );
+        assert_eq!(hosts_queried(&client), ["198.51.100.1", "192.0.2.53"]);
+    }
+
+    #[test]
+    fn follow_referral_with_ipv6_glue() {
+        let client = mock_client();
+        let mut referral = referral();
+        referral.additionals.push(ResourceRecord {
+            an_name: "ns.example.com".parse().unwrap(),
+            an_type: RecordType::AAAA,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::Aaaa(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53)),
+        });
+        client.transport().add_response("198.51.100.1", referral);
+        let mut answer = response();
+        answer.header.flags.set_aa(true);
+        answer
+            .answers
+            .push(address("www.example.com", Ipv4Addr::new(192, 0, 2, 1)));
+        client.transport().add_response("2001:db8::53", answer);
+
+        let lookup = ask(&client).unwrap();
+        assert_eq!(lookup.server.host, "ns.example.com");
+        assert_eq!(lookup.server.addr, "2001:db8::53".parse().ok());
+        assert_eq!(hosts_queried(&client), ["198.51.100.1", "2001:db8::53"]);
+    }
+
+    #[test]
+    fn ask_for_recursion() {
+        let mut client = mock_client();
+        ask(&client).unwrap_err();
+        client.set_recursion_desired(true);
+        ask(&client).unwrap_err();
+
+        let queries = client.transport().queries();
+        let attempts = client.config.attempts as usize;
+        assert!(!queries[0].1.header.flags.rd());
+        assert!(queries[attempts].1.header.flags.rd());
+    }
+
+    #[test]
+    fn stop_at_glueless_referral() {
+        let client = mock_client();
+        client.transport().add_response("198.51.100.1", referral());
+
+        assert!(matches!(ask(&client), Err(ResolveError::NoAnswer)));
+        assert_eq!(hosts_queried(&client), ["198.51.100.1"]);
+    }
+
+    #[test]
+    fn retry_server_failures_within_attempts() {
+        let client = mock_client();
+        let mut servfail = response();
+        servfail.header.flags.set_rcode(Rcode::ServFail);
+        client.transport().add_response("198.51.100.1", servfail);
+        assert!(matches!(ask(&client), Err(ResolveError::ServFail)));
+        assert_eq!(hosts_queried(&client).len(), 3);
+
+        let client = mock_client();
+        assert!(matches!(ask(&client), Err(ResolveError::Timeout)));
+        assert_eq!(hosts_queried(&client).len(), 3);
+    }
 }
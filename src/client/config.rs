@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+/// Timeouts and retry policy of a resolver, applied to every query it
+/// sends whatever the transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolverConfig {
+    /// Time to wait for the response to a single query
+    pub timeout: Duration,
+    /// Time after which a lookup gives up, retries and referrals included,
+    /// or `None` to be bounded by the attempts only
+    pub deadline: Option<Duration>,
+    /// Number of queries a lookup sends at most, referrals included
+    pub attempts: u32,
+    /// Wait before retrying a query that failed, doubled on each further
+    /// failure
+    pub initial_backoff: Duration,
+    /// Longest wait before retrying a query
+    pub max_backoff: Duration,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        ResolverConfig {
+            timeout: Duration::from_secs(2),
+            deadline: Some(Duration::from_secs(10)),
+            attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ResolverConfig {
+    /// Wait before the next query after `failures` failed ones in a row.
+    /// The second half of the wait is random so that clients failing
+    /// together don't retry together
+    pub fn backoff(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u32.checked_shl(failures - 1).unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(Duration::MAX)
+            .min(self.max_backoff);
+        backoff / 2 + backoff.mul_f64(rand::random::<f64>() / 2.0)
+    }
+
+    /// Time a query started at `now` may wait for its response, within both
+    /// the per-query timeout and the deadline of a lookup started at
+    /// `start`. `None` once the deadline has passed
+    pub fn attempt_timeout(&self, start: Instant, now: Instant) -> Option<Duration> {
+        let Some(deadline) = self.deadline else {
+            return Some(self.timeout);
+        };
+        let remaining = (start + deadline).saturating_duration_since(now);
+        if remaining.is_zero() {
+            return None;
+        }
+        Some(self.timeout.min(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_off_exponentially_with_jitter() {
+        let config = ResolverConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            ..ResolverConfig::default()
+        };
+        assert_eq!(config.backoff(0), Duration::ZERO);
+        for (failures, base) in [(1, 100), (2, 200), (3, 400), (4, 500), (40, 500)] {
+            let backoff = config.backoff(failures);
+            let base = Duration::from_millis(base);
+            assert!(backoff >= base / 2 && backoff <= base, "{:?}", backoff);
+        }
+    }
+
+    #[test]
+    fn bound_attempts_by_deadline() {
+        let config = ResolverConfig {
+            timeout: Duration::from_secs(2),
+            deadline: Some(Duration::from_secs(5)),
+            ..ResolverConfig::default()
+        };
+        let start = Instant::now();
+        assert_eq!(
+            config.attempt_timeout(start, start),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            config.attempt_timeout(start, start + Duration::from_secs(4)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            config.attempt_timeout(start, start + Duration::from_secs(5)),
+            None
+        );
+
+        let config = ResolverConfig {
+            deadline: None,
+            ..config
+        };
+        assert_eq!(
+            config.attempt_timeout(start, start + Duration::from_secs(60)),
+            Some(Duration::from_secs(2))
+        );
+    }
+}
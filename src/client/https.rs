@@ -0,0 +1,771 @@
+//! DNS over HTTPS (RFC 8484), sending messages in HTTP/2 requests of type
+//! `application/dns-message`
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use http::header::{ACCEPT, AGE, CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderMap, Request, StatusCode};
+use rustls::pki_types::ServerName;
+use rustls::ClientConfig;
+use tokio::net::TcpStream;
+use tokio::runtime::Runtime;
+use tokio_rustls::TlsConnector;
+
+use crate::client::tls::TlsConfig;
+use crate::client::transport::{is_response_to, Server, Transport};
+use crate::error::ResolveError;
+use crate::message::{DnsMessage, MAX_MESSAGE_SIZE};
+
+/// Port DNS over HTTPS servers listen on
+pub const DOH_PORT: u16 = 443;
+
+/// Media type of DNS messages in HTTP requests and responses
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// HTTP method queries are sent with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpsMethod {
+    /// Query in the body of a POST request
+    #[default]
+    Post,
+    /// Query in the `dns` parameter of a GET request, in base64url, which
+    /// HTTP caches on the way can answer
+    Get,
+}
+
+/// How DNS over HTTPS servers are reached
+#[derive(Debug, Clone)]
+pub struct HttpsConfig {
+    /// Authentication of the servers and time connections are kept open
+    pub tls: TlsConfig,
+    /// Path of the URI template of the servers
+    pub path: String,
+    /// HTTP method queries are sent with
+    pub method: HttpsMethod,
+    /// Largest number of responses cached, 0 turning the cache off
+    pub cache_size: usize,
+}
+
+impl Default for HttpsConfig {
+    fn default() -> Self {
+        HttpsConfig {
+            tls: TlsConfig::default(),
+            path: "/dns-query".to_string(),
+            method: HttpsMethod::default(),
+            cache_size: 1024,
+        }
+    }
+}
+
+/// Address and name of a server, and query sent to it with ID 0
+type CacheKey = (SocketAddr, String, Vec<u8>);
+
+/// Response kept until its HTTP freshness or its records expire
+#[derive(Debug)]
+struct CachedResponse {
+    response: DnsMessage,
+    /// Age of the response when it was received, in seconds
+    age: u32,
+    received: Instant,
+    lifetime: Duration,
+}
+
+impl CachedResponse {
+    /// Whether the response may still be served
+    fn is_fresh(&self) -> bool {
+        self.received.elapsed() < self.lifetime
+    }
+}
+
+/// Client of DNS over HTTPS servers, keeping one HTTP/2 connection open per
+/// server and caching responses as allowed by both their HTTP headers and
+/// the TTLs of their records
+#[derive(Debug)]
+pub struct HttpsClient {
+    config: HttpsConfig,
+    client_config: Arc<ClientConfig>,
+    /// Runtime driving the HTTP/2 connections, between queries too
+    runtime: Runtime,
+    /// Open connections and the time they were last used
+    connections: Mutex<HashMap<SocketAddr, (SendRequest<Bytes>, Instant)>>,
+    /// Responses by the server and the query they answer, sent with ID 0
+    cache: Mutex<HashMap<CacheKey, CachedResponse>>,
+}
+
+impl HttpsClient {
+    /// Create a client authenticating servers as configured
+    pub fn new(config: HttpsConfig) -> io::Result<HttpsClient> {
+        let mut client_config = config
+            .tls
+            .client_config()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        client_config.alpn_protocols = vec![b"h2".to_vec()];
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+
+        Ok(HttpsClient {
+            config,
+            client_config: Arc::new(client_config),
+            runtime,
+            connections: Mutex::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Send a query to the address of a server, `server_name` standing for
+    /// it unless the configuration names it
+    fn exchange_at(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        query: &DnsMessage,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        // ID 0 makes the same questions the same requests to HTTP caches
+        let mut request = query.clone();
+        request.header.id = 0;
+        let wire = request.to_be_bytes()?;
+        let server_name = self
+            .config
+            .tls
+            .server_name
+            .as_deref()
+            .unwrap_or(server_name);
+        // Servers may answer the same query differently, as along referrals
+        let key = (addr, server_name.to_string(), wire);
+
+        if let Some(mut response) = self.cached(&key) {
+            response.header.id = query.header.id;
+            return Ok(response);
+        }
+        let wire = &key.2;
+        let (headers, body) = self
+            .runtime
+            .block_on(async {
+                tokio::time::timeout(timeout, self.fetch(addr, server_name, wire)).await
+            })
+            .map_err(|_| ResolveError::Timeout)??;
+        let mut response = DnsMessage::parse(&body)?;
+        if !is_response_to(&response, &request) {
+            return Err(ResolveError::Network(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("response from {} to another question", addr),
+            )));
+        }
+
+        let age = header_seconds(&headers, AGE).unwrap_or(0);
+        if let Some(lifetime) = freshness(&headers, &response, age) {
+            let cached = CachedResponse {
+                response: response.clone(),
+                age,
+                received: Instant::now(),
+                lifetime,
+            };
+            self.cache(key, cached);
+        }
+        age_records(&mut response, age);
+        response.header.id = query.header.id;
+        Ok(response)
+    }
+
+    /// Keep a response to a query, dropping the expired ones and, when the
+    /// cache is full, the one that would expire first
+    fn cache(&self, key: CacheKey, cached: CachedResponse) {
+        if self.config.cache_size == 0 {
+            return;
+        }
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.is_fresh());
+        if cache.len() >= self.config.cache_size && !cache.contains_key(&key) {
+            let expiring = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.received + cached.lifetime)
+                .map(|(key, _)| key.clone());
+            if let Some(expiring) = expiring {
+                cache.remove(&expiring);
+            }
+        }
+        cache.insert(key, cached);
+    }
+
+    /// Fresh cached response to a query, its TTLs lowered by its age
+    fn cached(&self, key: &CacheKey) -> Option<DnsMessage> {
+        let mut cache = self.cache.lock().unwrap();
+        let cached = cache.get(key)?;
+        if !cached.is_fresh() {
+            cache.remove(key);
+            return None;
+        }
+        let elapsed = cached.received.elapsed();
+        let mut response = cached.response.clone();
+        let age = cached.age.saturating_add(elapsed.as_secs() as u32);
+        age_records(&mut response, age);
+        Some(response)
+    }
+
+    /// Send a query in an HTTP request and read the response body. A
+    /// connection left open by an earlier query is used when there is one,
+    /// and replaced by a new one if it has been closed meanwhile
+    async fn fetch(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        wire: &[u8],
+    ) -> io::Result<(HeaderMap, Vec<u8>)> {
+        let idle = self
+            .connections
+            .lock()
+            .unwrap()
+            .remove(&addr)
+            .filter(|(_, last_used)| last_used.elapsed() < self.config.tls.idle_timeout)
+            .map(|(sender, _)| sender);
+
+        // A connection closed by the server fails to become ready
+        let sender = match idle {
+            Some(sender) => sender.ready().await.ok(),
+            None => None,
+        };
+        let sender = match sender {
+            Some(sender) => sender,
+            None => {
+                let sender = self.connect(addr, server_name).await?;
+                sender.ready().await.map_err(h2_error)?
+            }
+        };
+        let body = match self.config.method {
+            HttpsMethod::Post => Some(Bytes::copy_from_slice(wire)),
+            HttpsMethod::Get => None,
+        };
+        let request = self.request(addr, server_name, wire)?;
+        let response = send_request(sender.clone(), request, body).await;
+
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(addr, (sender, Instant::now()));
+        response
+    }
+
+    /// Open an HTTP/2 connection to a server whose certificate must be valid
+    /// for `server_name`
+    async fn connect(&self, addr: SocketAddr, server_name: &str) -> io::Result<SendRequest<Bytes>> {
+        let name = ServerName::try_from(server_name.to_string())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        let stream = TlsConnector::from(self.client_config.clone())
+            .connect(name, stream)
+            .await?;
+        if stream.get_ref().1.alpn_protocol() != Some(b"h2") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} does not speak HTTP/2", addr),
+            ));
+        }
+        let (sender, connection) = h2::client::handshake(stream).await.map_err(h2_error)?;
+        // The connection lives until every sender is dropped or it fails
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+        Ok(sender)
+    }
+
+    /// HTTP request carrying a query, as configured
+    fn request(&self, addr: SocketAddr, server_name: &str, wire: &[u8]) -> io::Result<Request<()>> {
+        let host = match server_name.parse::<IpAddr>() {
+            Ok(IpAddr::V6(addr)) => format!("[{}]", addr),
+            _ => server_name.to_string(),
+        };
+        let authority = match addr.port() {
+            DOH_PORT => host,
+            port => format!("{}:{}", host, port),
+        };
+        let uri = format!("https://{}{}", authority, self.config.path);
+        let request = match self.config.method {
+            HttpsMethod::Post => Request::post(uri).header(CONTENT_TYPE, DNS_MESSAGE),
+            HttpsMethod::Get => {
+                let separator = if self.config.path.contains('?') {
+                    '&'
+                } else {
+                    '?'
+                };
+                Request::get(format!(
+                    "{}{}dns={}",
+                    uri,
+                    separator,
+                    URL_SAFE_NO_PAD.encode(wire)
+                ))
+            }
+        };
+        request
+            .header(ACCEPT, DNS_MESSAGE)
+            .body(())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+}
+
+impl Transport for HttpsClient {
+    /// Send a query to a server and wait at most `timeout` for its response,
+    /// unless a fresh response to the same query is cached. The TTLs of the
+    /// records are lowered by the age of the response
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let addr = server.socket_addr(DOH_PORT)?;
+        self.exchange_at(addr, &server.host, query, timeout)
+    }
+}
+
+/// Send a request, with a body if any, and read the DNS message of the
+/// response
+async fn send_request(
+    mut sender: SendRequest<Bytes>,
+    request: Request<()>,
+    body: Option<Bytes>,
+) -> io::Result<(HeaderMap, Vec<u8>)> {
+    let (response, mut stream) = sender
+        .send_request(request, body.is_none())
+        .map_err(h2_error)?;
+    if let Some(body) = body {
+        stream.send_data(body, true).map_err(h2_error)?;
+    }
+    let (parts, mut body) = response.await.map_err(h2_error)?.into_parts();
+    if parts.status != StatusCode::OK {
+        return Err(io::Error::other(format!("HTTP status {}", parts.status)));
+    }
+    let content_type = parts.headers.get(CONTENT_TYPE);
+    if content_type.is_none_or(|content_type| content_type != DNS_MESSAGE) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected content type {:?}", content_type),
+        ));
+    }
+
+    let mut message = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(h2_error)?;
+        body.flow_control()
+            .release_capacity(chunk.len())
+            .map_err(h2_error)?;
+        message.extend_from_slice(&chunk);
+        if message.len() > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response larger than a DNS message",
+            ));
+        }
+    }
+    Ok((parts.headers, message))
+}
+
+/// Time a response may be cached for: its HTTP freshness lifetime, within
+/// the TTLs of its records counted from when it was produced `age` seconds
+/// ago (RFC 8484 section 5.1). `None` when it must not be cached
+fn freshness(headers: &HeaderMap, response: &DnsMessage, age: u32) -> Option<Duration> {
+    let mut max_age = None;
+    for directive in headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+    {
+        let directive = directive.trim().to_ascii_lowercase();
+        match directive.split_once('=') {
+            Some(("max-age", seconds)) => max_age = seconds.trim_matches('"').parse::<u32>().ok(),
+            None if directive == "no-store" || directive == "no-cache" => return None,
+            _ => {}
+        }
+    }
+
+    let min_ttl = response
+        .answers
+        .iter()
+        .chain(&response.authorities)
+        .chain(&response.additionals)
+        .map(|rr| rr.an_ttl)
+        .min()?;
+    let lifetime = max_age.map_or(min_ttl, |max_age| max_age.min(min_ttl));
+    let lifetime = lifetime.saturating_sub(age);
+    (lifetime > 0).then(|| Duration::from_secs(lifetime.into()))
+}
+
+/// Lower the TTLs of the records of a response by its age
+fn age_records(response: &mut DnsMessage, age: u32) {
+    for rr in response
+        .answers
+        .iter_mut()
+        .chain(&mut response.authorities)
+        .chain(&mut response.additionals)
+    {
+        rr.an_ttl = rr.an_ttl.saturating_sub(age);
+    }
+}
+
+/// Value of a header holding a number of seconds
+fn header_seconds(headers: &HeaderMap, name: http::HeaderName) -> Option<u32> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn h2_error(err: h2::Error) -> io::Error {
+    if err.is_io() {
+        err.into_io().unwrap()
+    } else {
+        io::Error::other(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::name::Name;
+    use crate::rdata::RData;
+    use crate::rr::ResourceRecord;
+    use crate::types::{Class, RecordType};
+    use http::{Method, Response};
+    use rcgen::CertifiedKey;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{crypto, ServerConfig};
+    use std::net::Ipv4Addr;
+    use std::thread;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// DNS over HTTPS server answering every query with an address valid
+    /// for 300 seconds, with the given cache headers. Queries for names
+    /// under `spoofed.` are answered for another name
+    struct StubServer {
+        addr: SocketAddr,
+        certificate: CertificateDer<'static>,
+        connections: Arc<Mutex<usize>>,
+        /// Method and URI of the requests received
+        requests: Arc<Mutex<Vec<(Method, String)>>>,
+    }
+
+    impl StubServer {
+        fn start(cache_control: &'static str, age: u32) -> StubServer {
+            let CertifiedKey { cert, signing_key } =
+                rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(signing_key.serialize_der()));
+            let provider = Arc::new(crypto::ring::default_provider());
+            let mut server_config = ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![cert.der().clone()], key)
+                .unwrap();
+            server_config.alpn_protocols = vec![b"h2".to_vec()];
+            let acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let listener = runtime
+                .block_on(TcpListener::bind((Ipv4Addr::LOCALHOST, 0)))
+                .unwrap();
+            let addr = listener.local_addr().unwrap();
+            let connections = Arc::new(Mutex::new(0));
+            let requests = Arc::new(Mutex::new(vec![]));
+            let (accepted, received) = (connections.clone(), requests.clone());
+            thread::spawn(move || {
+                runtime.block_on(async move {
+                    loop {
+                        let (stream, _) = listener.accept().await.unwrap();
+                        *accepted.lock().unwrap() += 1;
+                        let acceptor = acceptor.clone();
+                        let received = received.clone();
+                        tokio::spawn(async move {
+                            let stream = acceptor.accept(stream).await.unwrap();
+                            let mut connection = h2::server::handshake(stream).await.unwrap();
+                            // Requests are answered apart from the connection, which
+                            // must keep being polled for their bodies to arrive
+                            while let Some(Ok((request, respond))) = connection.accept().await {
+                                let received = received.clone();
+                                tokio::spawn(answer(
+                                    request,
+                                    respond,
+                                    received,
+                                    cache_control,
+                                    age,
+                                ));
+                            }
+                        });
+                    }
+                })
+            });
+
+            StubServer {
+                addr,
+                certificate: cert.der().clone(),
+                connections,
+                requests,
+            }
+        }
+
+        fn client(&self, method: HttpsMethod) -> HttpsClient {
+            self.client_with_cache(method, HttpsConfig::default().cache_size)
+        }
+
+        fn client_with_cache(&self, method: HttpsMethod, cache_size: usize) -> HttpsClient {
+            HttpsClient::new(HttpsConfig {
+                tls: TlsConfig {
+                    server_name: Some("localhost".to_string()),
+                    root_certificates: vec![self.certificate.clone()],
+                    ..TlsConfig::default()
+                },
+                method,
+                cache_size,
+                ..HttpsConfig::default()
+            })
+            .unwrap()
+        }
+
+        fn connections(&self) -> usize {
+            *self.connections.lock().unwrap()
+        }
+
+        fn requests(&self) -> Vec<(Method, String)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// Answer a query with an address valid for 300 seconds
+    async fn answer(
+        request: Request<h2::RecvStream>,
+        mut respond: h2::server::SendResponse<Bytes>,
+        received: Arc<Mutex<Vec<(Method, String)>>>,
+        cache_control: &'static str,
+        age: u32,
+    ) {
+        let (parts, mut body) = request.into_parts();
+        received
+            .lock()
+            .unwrap()
+            .push((parts.method.clone(), parts.uri.to_string()));
+        let query = if parts.method == Method::POST {
+            assert_eq!(parts.headers[CONTENT_TYPE], DNS_MESSAGE);
+            let mut query = vec![];
+            while let Some(chunk) = body.data().await {
+                query.extend_from_slice(&chunk.unwrap());
+            }
+            query
+        } else {
+            let dns = parts.uri.query().unwrap().strip_prefix("dns=");
+            URL_SAFE_NO_PAD.decode(dns.unwrap()).unwrap()
+        };
+
+        let mut message = DnsMessage::parse(&query).unwrap();
+        assert_eq!(message.header.id, 0);
+        message.header.flags.set_qr(true);
+        if message.questions[0]
+            .q_name
+            .to_string()
+            .starts_with("spoofed.")
+        {
+            message.questions[0].q_name = "example.net".parse().unwrap();
+        }
+        message.answers.push(ResourceRecord {
+            an_name: message.questions[0].q_name.clone(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+        let response = Response::builder()
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(CACHE_CONTROL, cache_control)
+            .header(AGE, age)
+            .body(())
+            .unwrap();
+        let mut stream = respond.send_response(response, false).unwrap();
+        stream
+            .send_data(message.to_be_bytes().unwrap().into(), true)
+            .unwrap();
+    }
+
+    fn exchange(client: &HttpsClient, server: &StubServer, name: &str, id: u16) -> DnsMessage {
+        let query = DnsMessage::query(name.parse::<Name>().unwrap())
+            .id(id)
+            .build();
+        let timeout = Duration::from_secs(5);
+        let response = client
+            .exchange(&query, &Server::from(server.addr), timeout)
+            .unwrap();
+        assert_eq!(response.header.id, id);
+        assert_eq!(response.questions, query.questions);
+        response
+    }
+
+    #[test]
+    fn post_queries_over_one_connection() {
+        let server = StubServer::start("no-store", 0);
+        let client = server.client(HttpsMethod::Post);
+        exchange(&client, &server, "example.com", 1);
+        exchange(&client, &server, "example.com", 2);
+
+        let uri = format!("https://localhost:{}/dns-query", server.addr.port());
+        assert_eq!(
+            server.requests(),
+            [(Method::POST, uri.clone()), (Method::POST, uri)]
+        );
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[test]
+    fn get_queries_in_base64url() {
+        let server = StubServer::start("no-store", 0);
+        let client = server.client(HttpsMethod::Get);
+        let response = exchange(&client, &server, "example.com", 1);
+        assert_eq!(response.answers[0].an_ttl, 300);
+
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(0)
+            .build();
+        let uri = format!(
+            "https://localhost:{}/dns-query?dns={}",
+            server.addr.port(),
+            URL_SAFE_NO_PAD.encode(query.to_be_bytes().unwrap())
+        );
+        assert_eq!(server.requests(), [(Method::GET, uri)]);
+    }
+
+    #[test]
+    fn serve_fresh_responses_from_cache() {
+        let server = StubServer::start("public, max-age=60", 10);
+        let client = server.client(HttpsMethod::Post);
+        let first = exchange(&client, &server, "example.com", 1);
+        let second = exchange(&client, &server, "example.com", 2);
+        exchange(&client, &server, "example.org", 3);
+
+        // Records are as old as the response they come in
+        assert_eq!(first.answers[0].an_ttl, 290);
+        assert!((289..=290).contains(&second.answers[0].an_ttl));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn cache_responses_per_server() {
+        let servers = [
+            StubServer::start("max-age=60", 0),
+            StubServer::start("max-age=60", 0),
+        ];
+        let client = HttpsClient::new(HttpsConfig {
+            tls: TlsConfig {
+                server_name: Some("localhost".to_string()),
+                root_certificates: servers
+                    .iter()
+                    .map(|server| server.certificate.clone())
+                    .collect(),
+                ..TlsConfig::default()
+            },
+            ..HttpsConfig::default()
+        })
+        .unwrap();
+
+        exchange(&client, &servers[0], "example.com", 1);
+        exchange(&client, &servers[1], "example.com", 2);
+        exchange(&client, &servers[0], "example.com", 3);
+        exchange(&client, &servers[1], "example.com", 4);
+        assert_eq!(servers[0].requests().len(), 1);
+        assert_eq!(servers[1].requests().len(), 1);
+    }
+
+    #[test]
+    fn bound_cache_size() {
+        let server = StubServer::start("max-age=60", 0);
+        let client = server.client_with_cache(HttpsMethod::Post, 2);
+        // An expired response is swept by the next insertion
+        let expired = DnsMessage::new("expired.example".parse().unwrap());
+        client.cache.lock().unwrap().insert(
+            (
+                server.addr,
+                "localhost".to_string(),
+                expired.to_be_bytes().unwrap(),
+            ),
+            CachedResponse {
+                response: expired,
+                age: 0,
+                received: Instant::now(),
+                lifetime: Duration::ZERO,
+            },
+        );
+
+        exchange(&client, &server, "a.example", 1);
+        assert_eq!(client.cache.lock().unwrap().len(), 1);
+        exchange(&client, &server, "b.example", 2);
+        exchange(&client, &server, "c.example", 3);
+        assert_eq!(client.cache.lock().unwrap().len(), 2);
+
+        // The response expiring first made way for the last one
+        exchange(&client, &server, "c.example", 4);
+        assert_eq!(server.requests().len(), 3);
+        exchange(&client, &server, "a.example", 5);
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[test]
+    fn reject_response_to_another_question() {
+        let server = StubServer::start("max-age=60", 0);
+        let client = server.client(HttpsMethod::Post);
+        let query = DnsMessage::query("spoofed.example.com".parse().unwrap())
+            .id(1)
+            .build();
+        let result = client.exchange(&query, &Server::from(server.addr), Duration::from_secs(5));
+        assert!(matches!(
+            result,
+            Err(ResolveError::Network(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+        assert!(client.cache.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bound_freshness_by_record_ttls() {
+        let mut response = DnsMessage::new("example.com".parse().unwrap());
+        response.answers.push(ResourceRecord {
+            an_name: "example.com".parse().unwrap(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+        let headers = |cache_control: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CACHE_CONTROL, cache_control.parse().unwrap());
+            headers
+        };
+
+        let seconds = |seconds| Some(Duration::from_secs(seconds));
+        assert_eq!(freshness(&HeaderMap::new(), &response, 0), seconds(300));
+        assert_eq!(
+            freshness(&headers("max-age=600"), &response, 0),
+            seconds(300)
+        );
+        assert_eq!(
+            freshness(&headers("max-age=60"), &response, 10),
+            seconds(50)
+        );
+        assert_eq!(
+            freshness(&headers("max-age=\"60\""), &response, 0),
+            seconds(60)
+        );
+        assert_eq!(freshness(&HeaderMap::new(), &response, 300), None);
+        assert_eq!(
+            freshness(&headers("no-cache, max-age=60"), &response, 0),
+            None
+        );
+        assert_eq!(freshness(&headers("No-Store"), &response, 0), None);
+
+        response.answers.clear();
+        assert_eq!(freshness(&HeaderMap::new(), &response, 0), None);
+    }
+}
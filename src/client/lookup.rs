@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use crate::client::transport::Server;
+use crate::error::ResolveError;
+use crate::header::Rcode;
+use crate::message::DnsMessage;
+use crate::name::Name;
+use crate::question::Question;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::RecordType;
+
+/// Result of a successful lookup
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    /// Question that was asked
+    pub question: Question,
+    /// Name the records belong to, reached from the name asked for by
+    /// following the CNAME records of the answer
+    pub canonical_name: Name,
+    /// Records of the answer section, including the CNAME records leading
+    /// to the canonical name
+    pub records: Vec<ResourceRecord>,
+    /// Server that gave the answer
+    pub server: Server,
+    /// Whether the answer is authoritative
+    pub authoritative: bool,
+    /// Time taken by the whole lookup, referrals and retries included
+    pub elapsed: Duration,
+    /// Round-trip time of the query that got the answer
+    pub rtt: Duration,
+    /// Response carrying the answer
+    pub response: DnsMessage,
+}
+
+impl Lookup {
+    /// Turn a response into a lookup, or into the error its response code
+    /// or empty answer stands for
+    pub fn from_response(
+        question: Question,
+        response: DnsMessage,
+        server: Server,
+        elapsed: Duration,
+        rtt: Duration,
+    ) -> Result<Lookup, ResolveError> {
+        let canonical_name = canonical_name(&question, &response.answers);
+        match response.rcode() {
+            Rcode::NoError => {}
+            Rcode::NXDomain => {
+                return Err(ResolveError::NxDomain {
+                    name: canonical_name,
+                })
+            }
+            Rcode::ServFail => return Err(ResolveError::ServFail),
+            Rcode::Refused => return Err(ResolveError::Refused),
+            rcode => return Err(ResolveError::ServerError(rcode)),
+        }
+
+        let lookup = Lookup {
+            question,
+            canonical_name,
+            records: response.answers.clone(),
+            server,
+            authoritative: response.header.flags.aa(),
+            elapsed,
+            rtt,
+            response,
+        };
+        if lookup.rdata().next().is_none() {
+            return Err(ResolveError::NoData {
+                name: lookup.canonical_name,
+                q_type: lookup.question.q_type,
+            });
+        }
+        Ok(lookup)
+    }
+
+    /// Records of the type asked for held by the canonical name
+    pub fn answers(&self) -> impl Iterator<Item = &ResourceRecord> {
+        self.records.iter().filter(|rr| {
+            rr.an_name == self.canonical_name
+                && (rr.an_type == self.question.q_type || self.question.q_type == RecordType::ANY)
+        })
+    }
+
+    /// Rdata of the records of the type asked for
+    pub fn rdata(&self) -> impl Iterator<Item = &RData> {
+        self.answers().map(|rr| &rr.an_rdata)
+    }
+
+    /// Time the answer may be cached for, the lowest TTL of the records
+    pub fn ttl(&self) -> Option<u32> {
+        self.records.iter().map(|rr| rr.an_ttl).min()
+    }
+}
+
+/// Follow the CNAME records of an answer from the name asked for
+fn canonical_name(question: &Question, answers: &[ResourceRecord]) -> Name {
+    let mut name = question.q_name.clone();
+    if question.q_type == RecordType::CNAME {
+        return name;
+    }
+    // Each step uses up a record, which bounds the walk of a CNAME loop
+    for _ in 0..answers.len() {
+        let target = answers.iter().find_map(|rr| match &rr.an_rdata {
+            RData::Cname(target) if rr.an_name == name => Some(target),
+            _ => None,
+        });
+        match target {
+            Some(target) => name = target.clone(),
+            None => break,
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Class;
+    use std::net::Ipv4Addr;
+
+    fn name(text: &str) -> Name {
+        text.parse().unwrap()
+    }
+
+    fn record(owner: &str, an_rdata: RData) -> ResourceRecord {
+        let an_type = match an_rdata {
+            RData::A(_) => RecordType::A,
+            RData::Cname(_) => RecordType::CNAME,
+            _ => unreachable!(),
+        };
+        ResourceRecord {
+            an_name: name(owner),
+            an_type,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata,
+        }
+    }
+
+    fn lookup(response: DnsMessage) -> Result<Lookup, ResolveError> {
+        let question = response.question().unwrap().clone();
+        let server = Server::new("127.0.0.1");
+        Lookup::from_response(question, response, server, Duration::ZERO, Duration::ZERO)
+    }
+
+    fn response(answers: Vec<ResourceRecord>) -> DnsMessage {
+        let mut response = DnsMessage::new(name("www.example.com"));
+        response.header.flags.set_qr(true);
+        response.answers = answers;
+        response
+    }
+
+    #[test]
+    fn follow_cname_chain() {
+        let mut answers = vec![
+            record("www.example.com", RData::Cname(name("web.example.com"))),
+            record("web.example.com", RData::Cname(name("cdn.example.net"))),
+            record("cdn.example.net", RData::A(Ipv4Addr::new(192, 0, 2, 1))),
+        ];
+        answers[1].an_ttl = 60;
+        let lookup = lookup(response(answers)).unwrap();
+        assert_eq!(lookup.canonical_name, name("cdn.example.net"));
+        assert_eq!(
+            lookup.rdata().collect::<Vec<_>>(),
+            [&RData::A(Ipv4Addr::new(192, 0, 2, 1))]
+        );
+        assert_eq!(lookup.ttl(), Some(60));
+        assert!(!lookup.authoritative);
+    }
+
+    #[test]
+    fn stop_at_cname_loop() {
+        let answers = vec![
+            record("www.example.com", RData::Cname(name("web.example.com"))),
+            record("web.example.com", RData::Cname(name("www.example.com"))),
+        ];
+        assert!(matches!(
+            lookup(response(answers)),
+            Err(ResolveError::NoData { .. })
+        ));
+    }
+
+    #[test]
+    fn report_error_response_codes() {
+        let mut nxdomain = response(vec![record(
+            "www.example.com",
+            RData::Cname(name("gone.example.com")),
+        )]);
+        nxdomain.header.flags.set_rcode(Rcode::NXDomain);
+        match lookup(nxdomain) {
+            Err(ResolveError::NxDomain { name: missing }) => {
+                assert_eq!(missing, name("gone.example.com"))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let mut refused = response(vec![]);
+        refused.header.flags.set_rcode(Rcode::Refused);
+        assert!(matches!(lookup(refused), Err(ResolveError::Refused)));
+
+        let mut servfail = response(vec![]);
+        servfail.header.flags.set_rcode(Rcode::ServFail);
+        assert!(matches!(lookup(servfail), Err(ResolveError::ServFail)));
+    }
+
+    #[test]
+    fn report_nodata() {
+        let answers = vec![record(
+            "www.example.com",
+            RData::Cname(name("web.example.com")),
+        )];
+        match lookup(response(answers)) {
+            Err(ResolveError::NoData {
+                name: owner,
+                q_type,
+            }) => {
+                assert_eq!(owner, name("web.example.com"));
+                assert_eq!(q_type, RecordType::A);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! DNS over QUIC (RFC 9250), sending each query on its own QUIC stream
+
+use spdlog::prelude::*;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::{ClientConfig, Connection, Endpoint, VarInt};
+use rustls::client::Resumption;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+use crate::client::tcp;
+use crate::client::tls::TlsConfig;
+use crate::client::transport::{is_response_to, Server, Transport};
+use crate::error::ResolveError;
+use crate::message::{DnsMessage, MAX_MESSAGE_SIZE};
+
+/// Port DNS over QUIC servers listen on
+pub const DOQ_PORT: u16 = 853;
+
+/// Application error code closing a connection that is no longer needed
+const DOQ_NO_ERROR: VarInt = VarInt::from_u32(0);
+
+/// How DNS over QUIC servers are reached
+#[derive(Debug, Clone)]
+pub struct QuicConfig {
+    /// Authentication of the servers and time connections are kept open
+    pub tls: TlsConfig,
+    /// Resume earlier TLS sessions with the servers, saving a round trip
+    pub session_resumption: bool,
+    /// Send queries in 0-RTT data when resuming a session, before the
+    /// handshake completes. Such queries may be replayed by an attacker
+    pub zero_rtt: bool,
+}
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        QuicConfig {
+            tls: TlsConfig::default(),
+            session_resumption: true,
+            zero_rtt: false,
+        }
+    }
+}
+
+/// Connection to a server and the time it was last used
+#[derive(Debug)]
+struct OpenConnection {
+    connection: Connection,
+    last_used: Instant,
+    /// Completes with whether the server accepted the 0-RTT data the
+    /// connection was opened with, if any
+    zero_rtt: Option<JoinHandle<bool>>,
+}
+
+/// Client of DNS over QUIC servers, keeping one connection open per server
+/// for reuse until it has been idle for too long
+#[derive(Debug)]
+pub struct QuicClient {
+    config: QuicConfig,
+    client_config: ClientConfig,
+    /// Runtime driving the QUIC endpoint and its connections
+    runtime: Runtime,
+    endpoint: Endpoint,
+    connections: Mutex<HashMap<SocketAddr, OpenConnection>>,
+}
+
+impl QuicClient {
+    /// Create a client authenticating servers as configured
+    pub fn new(config: QuicConfig) -> io::Result<QuicClient> {
+        let mut tls_config = config
+            .tls
+            .client_config()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        tls_config.alpn_protocols = vec![b"doq".to_vec()];
+        tls_config.enable_early_data = config.zero_rtt;
+        if !config.session_resumption {
+            tls_config.resumption = Resumption::disabled();
+        }
+        let quic_config = QuicClientConfig::try_from(tls_config)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        // An IPv6 socket reaches IPv4 servers too, where dual stack is allowed
+        let endpoint = runtime.block_on(async {
+            Endpoint::client((Ipv6Addr::UNSPECIFIED, 0).into())
+                .or_else(|_| Endpoint::client(([0, 0, 0, 0], 0).into()))
+        })?;
+
+        Ok(QuicClient {
+            config,
+            client_config: ClientConfig::new(Arc::new(quic_config)),
+            runtime,
+            endpoint,
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Send a query to the address of a server, `server_name` standing for
+    /// it unless the configuration names it
+    fn exchange_at(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        query: &DnsMessage,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        // The stream identifies the query, whose ID must be 0 (RFC 9250
+        // section 4.2.1)
+        let mut request = query.clone();
+        request.header.id = 0;
+        let wire = request.to_be_bytes()?;
+
+        let server_name = self
+            .config
+            .tls
+            .server_name
+            .as_deref()
+            .unwrap_or(server_name);
+        let bytes = self
+            .runtime
+            .block_on(async {
+                tokio::time::timeout(timeout, self.fetch(addr, server_name, &wire)).await
+            })
+            .map_err(|_| ResolveError::Timeout)??;
+        let mut response = DnsMessage::parse(&bytes)?;
+        if !is_response_to(&response, &request) {
+            return Err(ResolveError::Network(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("response from {} to another query", addr),
+            )));
+        }
+        response.header.id = query.header.id;
+        Ok(response)
+    }
+
+    /// Send a query on a new stream of a connection to a server and read the
+    /// response sent back on it
+    async fn fetch(&self, addr: SocketAddr, server_name: &str, wire: &[u8]) -> io::Result<Vec<u8>> {
+        let idle = self.connections.lock().unwrap().remove(&addr);
+        let idle = match idle {
+            Some(open) if open.last_used.elapsed() < self.config.tls.idle_timeout => Some(open),
+            Some(open) => {
+                open.connection.close(DOQ_NO_ERROR, b"");
+                None
+            }
+            None => None,
+        };
+
+        let reused = idle.is_some();
+        let mut open = match idle {
+            Some(open) => open,
+            None => self.connect(addr, server_name).await?,
+        };
+        let mut response = query_on_stream(&open.connection, wire).await;
+        if let Some(zero_rtt) = open.zero_rtt.take() {
+            // Streams of rejected 0-RTT data fail, and can be opened again
+            // once the handshake is complete
+            if !zero_rtt.await.unwrap_or(false) && response.is_err() {
+                debug!("0-RTT data rejected by {}", addr);
+                response = query_on_stream(&open.connection, wire).await;
+            }
+        } else if response.is_err() && reused && open.connection.close_reason().is_some() {
+            open = self.connect(addr, server_name).await?;
+            response = query_on_stream(&open.connection, wire).await;
+        }
+
+        open.last_used = Instant::now();
+        self.connections.lock().unwrap().insert(addr, open);
+        response
+    }
+
+    /// Open a connection to a server whose certificate must be valid for
+    /// `server_name`, in 0-RTT when enabled and a session can be resumed
+    async fn connect(&self, addr: SocketAddr, server_name: &str) -> io::Result<OpenConnection> {
+        let connecting = self
+            .endpoint
+            .connect_with(self.client_config.clone(), addr, server_name)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let connecting = if self.config.zero_rtt {
+            match connecting.into_0rtt() {
+                Ok((connection, accepted)) => {
+                    debug!("Sending 0-RTT data to {}", addr);
+                    return Ok(OpenConnection {
+                        connection,
+                        last_used: Instant::now(),
+                        zero_rtt: Some(tokio::spawn(accepted)),
+                    });
+                }
+                Err(connecting) => connecting,
+            }
+        } else {
+            connecting
+        };
+        Ok(OpenConnection {
+            connection: connecting.await.map_err(io::Error::other)?,
+            last_used: Instant::now(),
+            zero_rtt: None,
+        })
+    }
+}
+
+impl Transport for QuicClient {
+    /// Send a query to a server on a new stream and wait at most `timeout`
+    /// for its response. A connection left open by an earlier query is used
+    /// when there is one, and replaced by a new one if it has been closed
+    /// meanwhile
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let addr = server.socket_addr(DOQ_PORT)?;
+        self.exchange_at(addr, &server.host, query, timeout)
+    }
+}
+
+impl Drop for QuicClient {
+    fn drop(&mut self) {
+        self.endpoint.close(DOQ_NO_ERROR, b"");
+    }
+}
+
+/// Send a query on a new stream, prefixed by its length as over TCP, and
+/// read the response the server sends back on the same stream
+async fn query_on_stream(connection: &Connection, wire: &[u8]) -> io::Result<Vec<u8>> {
+    let (mut send, mut recv) = connection.open_bi().await.map_err(io::Error::other)?;
+    let mut frame = Vec::with_capacity(2 + wire.len());
+    tcp::write_frame(&mut frame, wire)?;
+    send.write_all(&frame).await?;
+    // The end of the stream tells the server no more data follows
+    send.finish().map_err(io::Error::other)?;
+
+    let frame = recv
+        .read_to_end(2 + MAX_MESSAGE_SIZE)
+        .await
+        .map_err(io::Error::other)?;
+    tcp::read_frame(&mut frame.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::name::Name;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{RecvStream, SendStream, ServerConfig};
+    use rcgen::CertifiedKey;
+    use rustls::crypto;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::net::Ipv4Addr;
+    use std::thread;
+
+    /// DNS over QUIC server echoing queries back as responses. Queries for
+    /// names under `spoofed.` are answered for another name
+    struct StubServer {
+        addr: SocketAddr,
+        certificate: CertificateDer<'static>,
+        connections: Arc<Mutex<usize>>,
+        /// ID of the queries received
+        queries: Arc<Mutex<Vec<u16>>>,
+    }
+
+    impl StubServer {
+        fn start() -> StubServer {
+            let CertifiedKey { cert, signing_key } =
+                rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(signing_key.serialize_der()));
+            let provider = Arc::new(crypto::ring::default_provider());
+            let mut tls_config = rustls::ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![cert.der().clone()], key)
+                .unwrap();
+            tls_config.alpn_protocols = vec![b"doq".to_vec()];
+            tls_config.max_early_data_size = u32::MAX;
+            let quic_config = QuicServerConfig::try_from(tls_config).unwrap();
+            let server_config = ServerConfig::with_crypto(Arc::new(quic_config));
+
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let endpoint = runtime
+                .block_on(async {
+                    Endpoint::server(server_config, (Ipv4Addr::LOCALHOST, 0).into())
+                })
+                .unwrap();
+            let addr = endpoint.local_addr().unwrap();
+            let connections = Arc::new(Mutex::new(0));
+            let queries = Arc::new(Mutex::new(vec![]));
+            let (accepted, received) = (connections.clone(), queries.clone());
+            thread::spawn(move || {
+                runtime.block_on(async move {
+                    while let Some(incoming) = endpoint.accept().await {
+                        *accepted.lock().unwrap() += 1;
+                        // Streams are accepted during the handshake, for 0-RTT data
+                        let (connection, _) = incoming.accept().unwrap().into_0rtt().unwrap();
+                        let received = received.clone();
+                        tokio::spawn(async move {
+                            while let Ok((send, recv)) = connection.accept_bi().await {
+                                tokio::spawn(answer(send, recv, received.clone()));
+                            }
+                        });
+                    }
+                })
+            });
+
+            StubServer {
+                addr,
+                certificate: cert.der().clone(),
+                connections,
+                queries,
+            }
+        }
+
+        fn client(&self, config: QuicConfig) -> QuicClient {
+            QuicClient::new(QuicConfig {
+                tls: TlsConfig {
+                    server_name: Some("localhost".to_string()),
+                    root_certificates: vec![self.certificate.clone()],
+                    ..config.tls
+                },
+                ..config
+            })
+            .unwrap()
+        }
+
+        fn connections(&self) -> usize {
+            *self.connections.lock().unwrap()
+        }
+
+        fn queries(&self) -> Vec<u16> {
+            self.queries.lock().unwrap().clone()
+        }
+    }
+
+    /// Send a query back as its response
+    async fn answer(mut send: SendStream, mut recv: RecvStream, queries: Arc<Mutex<Vec<u16>>>) {
+        let frame = recv.read_to_end(2 + MAX_MESSAGE_SIZE).await.unwrap();
+        let query = tcp::read_frame(&mut frame.as_slice()).unwrap();
+        let mut message = DnsMessage::parse(&query).unwrap();
+        queries.lock().unwrap().push(message.header.id);
+        message.header.flags.set_qr(true);
+        if message.questions[0]
+            .q_name
+            .to_string()
+            .starts_with("spoofed.")
+        {
+            message.questions[0].q_name = "example.org".parse().unwrap();
+        }
+        let mut frame = vec![];
+        tcp::write_frame(&mut frame, &message.to_be_bytes().unwrap()).unwrap();
+        send.write_all(&frame).await.unwrap();
+        send.finish().unwrap();
+        // Keep the stream until the client has read the response
+        let _ = send.stopped().await;
+    }
+
+    fn exchange(client: &QuicClient, server: &StubServer, id: u16) {
+        let query = DnsMessage::query("example.com".parse::<Name>().unwrap())
+            .id(id)
+            .build();
+        let timeout = Duration::from_secs(5);
+        let response = client
+            .exchange(&query, &Server::from(server.addr), timeout)
+            .unwrap();
+        assert!(response.header.flags.qr());
+        assert_eq!(response.header.id, id);
+    }
+
+    #[test]
+    fn send_queries_on_streams_of_one_connection() {
+        let server = StubServer::start();
+        let client = server.client(QuicConfig::default());
+        exchange(&client, &server, 1);
+        exchange(&client, &server, 2);
+        assert_eq!(server.queries(), [0, 0]);
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[test]
+    fn reject_response_to_another_question() {
+        let server = StubServer::start();
+        let client = server.client(QuicConfig::default());
+        let query = DnsMessage::query("spoofed.example.com".parse::<Name>().unwrap())
+            .id(1)
+            .build();
+        let result = client.exchange(&query, &Server::from(server.addr), Duration::from_secs(5));
+        assert!(matches!(
+            result,
+            Err(ResolveError::Network(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    /// Whether a new connection to the server sends a query in 0-RTT data
+    /// the server accepts
+    fn zero_rtt_accepted(client: &QuicClient, server: &StubServer) -> bool {
+        let query = DnsMessage::new("example.com".parse().unwrap())
+            .to_be_bytes()
+            .unwrap();
+        client.runtime.block_on(async {
+            let mut open = client.connect(server.addr, "localhost").await.unwrap();
+            query_on_stream(&open.connection, &query).await.unwrap();
+            match open.zero_rtt.take() {
+                Some(zero_rtt) => zero_rtt.await.unwrap(),
+                None => false,
+            }
+        })
+    }
+
+    #[test]
+    fn send_query_in_0rtt_data_of_resumed_session() {
+        let server = StubServer::start();
+        let config = QuicConfig {
+            zero_rtt: true,
+            ..QuicConfig::default()
+        };
+        let client = server.client(config.clone());
+        // No session to resume yet
+        assert!(!zero_rtt_accepted(&client, &server));
+        assert!(zero_rtt_accepted(&client, &server));
+
+        let client = server.client(QuicConfig {
+            session_resumption: false,
+            ..config
+        });
+        exchange(&client, &server, 1);
+        assert!(!zero_rtt_accepted(&client, &server));
+    }
+
+    #[test]
+    fn reconnect_after_idle_timeout() {
+        let server = StubServer::start();
+        let client = server.client(QuicConfig {
+            tls: TlsConfig {
+                idle_timeout: Duration::ZERO,
+                ..TlsConfig::default()
+            },
+            zero_rtt: true,
+            ..QuicConfig::default()
+        });
+        exchange(&client, &server, 1);
+        exchange(&client, &server, 2);
+        assert_eq!(server.queries(), [0, 0]);
+        assert_eq!(server.connections(), 2);
+    }
+}
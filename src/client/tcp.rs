@@ -0,0 +1,306 @@
+//! DNS over TCP, where each message is prefixed by its length in two octets
+//! (RFC 1035 section 4.2.2, RFC 7766)
+
+use spdlog::prelude::*;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::client::transport::{is_response_to, Server, Transport, DNS_PORT};
+use crate::client::udp::parse_response;
+use crate::error::ResolveError;
+use crate::message::DnsMessage;
+
+/// Write a message prefixed by its length
+pub fn write_frame(writer: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(message.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("message of {} bytes does not fit a frame", message.len()),
+        )
+    })?;
+    let mut frame = Vec::with_capacity(2 + message.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(message);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Read a message prefixed by its length
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    reader.read_exact(&mut len)?;
+    let mut message = vec![0; u16::from_be_bytes(len) as usize];
+    reader.read_exact(&mut message)?;
+    Ok(message)
+}
+
+/// Read a message prefixed by its length by `deadline`, however slowly its
+/// bytes trickle in, with `set_timeout` bounding each read of `reader` by
+/// the time left
+pub(crate) fn read_frame_by<R: Read>(
+    reader: &mut R,
+    deadline: Instant,
+    set_timeout: impl Fn(&R, Duration) -> io::Result<()>,
+) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    read_exact_by(reader, &mut len, deadline, &set_timeout)?;
+    let mut message = vec![0; u16::from_be_bytes(len) as usize];
+    read_exact_by(reader, &mut message, deadline, &set_timeout)?;
+    Ok(message)
+}
+
+/// Fill `buffer` from `reader` by `deadline`, checking it between partial
+/// reads
+fn read_exact_by<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    deadline: Instant,
+    set_timeout: &impl Fn(&R, Duration) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        set_timeout(reader, remaining)?;
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Connection to a DNS server over TCP. Several queries may be sent on it
+/// and several responses read back, as for pipelined queries or zone
+/// transfers
+#[derive(Debug)]
+pub struct TcpConnection {
+    stream: TcpStream,
+}
+
+impl TcpConnection {
+    /// Connect to a server, waiting at most `timeout` for the connection and
+    /// then for each read or write
+    pub fn connect(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<TcpConnection> {
+        let mut last_error = None;
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => {
+                    let connection = TcpConnection { stream };
+                    connection.set_timeout(timeout)?;
+                    return Ok(connection);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+        }))
+    }
+
+    /// Wait at most `timeout` for each following read or write
+    pub fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        self.stream.set_write_timeout(Some(timeout))
+    }
+
+    /// Address of the server
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Send a message
+    pub fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        write_frame(&mut self.stream, message)
+    }
+
+    /// Read the next message sent by the server
+    pub fn receive(&mut self) -> io::Result<Vec<u8>> {
+        read_frame(&mut self.stream)
+    }
+
+    /// Read the next message sent by the server by `deadline`
+    pub fn receive_by(&mut self, deadline: Instant) -> io::Result<Vec<u8>> {
+        read_frame_by(&mut self.stream, deadline, |stream, timeout| {
+            stream.set_read_timeout(Some(timeout))
+        })
+    }
+}
+
+/// Transport sending each query over a new TCP connection
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpTransport;
+
+impl Transport for TcpTransport {
+    /// Skip responses to other queries, as a server may send several, until
+    /// the timeout runs out, however slowly they trickle in
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let deadline = Instant::now() + timeout;
+        let addr = server.socket_addr(DNS_PORT)?;
+        debug!("Connecting to {} over TCP", addr);
+        let mut connection = TcpConnection::connect(addr, timeout)?;
+        connection.send(&query.to_be_bytes()?)?;
+        loop {
+            let response = parse_response(addr, &connection.receive_by(deadline)?)?;
+            if is_response_to(&response, query) {
+                return Ok(response);
+            }
+            debug!(
+                "Skipping response {} from {} to another query",
+                response.header.id, addr
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MAX_MESSAGE_SIZE;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn frame_round_trip() {
+        let mut stream = vec![];
+        write_frame(&mut stream, &[1, 2, 3]).unwrap();
+        write_frame(&mut stream, &[]).unwrap();
+        assert_eq!(stream, [0, 3, 1, 2, 3, 0, 0]);
+
+        let mut reader = stream.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), [1, 2, 3]);
+        assert_eq!(read_frame(&mut reader).unwrap(), [0u8; 0]);
+        let err = read_frame(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reject_truncated_frame() {
+        let err = read_frame(&mut [0, 4, 1, 2].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reject_oversized_message() {
+        let message = vec![0; MAX_MESSAGE_SIZE + 1];
+        let err = write_frame(&mut vec![], &message).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn read_several_responses_on_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let first = read_frame(&mut stream).unwrap();
+            let second = read_frame(&mut stream).unwrap();
+            // Both responses in a single write, answered in reverse order
+            let mut responses = vec![];
+            write_frame(&mut responses, &second).unwrap();
+            write_frame(&mut responses, &first).unwrap();
+            stream.write_all(&responses).unwrap();
+        });
+
+        let mut connection = TcpConnection::connect(addr, Duration::from_secs(5)).unwrap();
+        assert_eq!(connection.peer_addr().unwrap(), addr);
+        connection.send(b"first").unwrap();
+        connection.send(b"second").unwrap();
+        assert_eq!(connection.receive().unwrap(), b"second");
+        assert_eq!(connection.receive().unwrap(), b"first");
+        server.join().unwrap();
+        let err = connection.receive().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn time_out_on_responses_to_other_queries() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut other = DnsMessage::parse(&read_frame(&mut stream).unwrap()).unwrap();
+            other.header.id = other.header.id.wrapping_add(1);
+            other.header.flags.set_qr(true);
+            // Keep the client busy until it hangs up
+            while write_frame(&mut stream, &other.to_be_bytes().unwrap()).is_ok() {
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let sent = Instant::now();
+        let result = TcpTransport.exchange(&query, &Server::from(addr), Duration::from_millis(300));
+        assert!(matches!(result, Err(ResolveError::Timeout)));
+        assert!(sent.elapsed() < Duration::from_secs(2));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn time_out_on_trickling_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut response = DnsMessage::parse(&read_frame(&mut stream).unwrap()).unwrap();
+            response.header.flags.set_qr(true);
+            let mut frame = vec![];
+            write_frame(&mut frame, &response.to_be_bytes().unwrap()).unwrap();
+            // One byte at a time, each well within the timeout
+            for byte in frame {
+                if stream.write_all(&[byte]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let sent = Instant::now();
+        let result = TcpTransport.exchange(&query, &Server::from(addr), Duration::from_millis(300));
+        assert!(matches!(result, Err(ResolveError::Timeout)));
+        assert!(sent.elapsed() < Duration::from_millis(600));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn skip_responses_to_other_questions() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = DnsMessage::parse(&read_frame(&mut stream).unwrap()).unwrap();
+            let mut other = DnsMessage::query("example.org".parse().unwrap())
+                .id(query.header.id)
+                .build();
+            other.header.flags.set_qr(true);
+            write_frame(&mut stream, &other.to_be_bytes().unwrap()).unwrap();
+            let mut response = query;
+            response.header.flags.set_qr(true);
+            write_frame(&mut stream, &response.to_be_bytes().unwrap()).unwrap();
+        });
+
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let response = TcpTransport
+            .exchange(&query, &Server::from(addr), Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(response.question(), query.question());
+        server.join().unwrap();
+    }
+}
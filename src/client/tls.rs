@@ -0,0 +1,596 @@
+//! DNS over TLS (RFC 7858), carrying the TCP framing of messages inside a
+//! TLS session
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ring::digest;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore,
+    SignatureScheme, StreamOwned,
+};
+
+use crate::client::tcp;
+use crate::client::transport::{is_response_to, Server, Transport};
+use crate::error::ResolveError;
+use crate::message::DnsMessage;
+
+/// Port DNS over TLS servers listen on
+pub const DOT_PORT: u16 = 853;
+
+/// How DNS over TLS servers are authenticated and their connections kept
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Name the certificate of the server must be valid for, the name or
+    /// address the server is reached at by default
+    pub server_name: Option<String>,
+    /// SHA-256 digests of the SubjectPublicKeyInfo of accepted server keys.
+    /// When set, a server is authenticated by its key matching one of them
+    /// instead of by its certificate chain (RFC 7858 section 4.2)
+    pub spki_pins: Vec<[u8; 32]>,
+    /// Certificates trusted to issue server certificates, the Mozilla root
+    /// certificates when empty
+    pub root_certificates: Vec<CertificateDer<'static>>,
+    /// Time an unused connection is kept open for the next queries
+    pub idle_timeout: Duration,
+}
+
+impl TlsConfig {
+    /// Configuration of rustls authenticating servers as configured
+    pub(crate) fn client_config(&self) -> Result<ClientConfig, rustls::Error> {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+        if self.spki_pins.is_empty() {
+            let mut roots = RootCertStore::empty();
+            if self.root_certificates.is_empty() {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+            for certificate in &self.root_certificates {
+                roots.add(certificate.clone())?;
+            }
+            Ok(builder.with_root_certificates(roots).with_no_client_auth())
+        } else {
+            let verifier = PinnedKeyVerifier {
+                pins: self.spki_pins.clone(),
+                provider,
+            };
+            Ok(builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+                .with_no_client_auth())
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            server_name: None,
+            spki_pins: vec![],
+            root_certificates: vec![],
+            idle_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Client of DNS over TLS servers, keeping one connection open per server
+/// for reuse until it has been idle for too long
+#[derive(Debug)]
+pub struct TlsClient {
+    config: TlsConfig,
+    client_config: Arc<ClientConfig>,
+    /// Open connections and the time they were last used
+    connections: Mutex<HashMap<SocketAddr, (TlsConnection, Instant)>>,
+}
+
+/// TLS session with a DNS server
+#[derive(Debug)]
+pub struct TlsConnection {
+    stream: StreamOwned<ClientConnection, DeadlineStream>,
+}
+
+/// TCP stream whose reads and writes give up at a deadline. A read of the
+/// TLS session may read the socket several times, so each of those is bounded
+/// by the time left rather than by a timeout of its own
+#[derive(Debug)]
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    /// Time left until the deadline, an error once it has passed
+    fn remaining(&self) -> io::Result<Duration> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        Ok(remaining)
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl TlsConnection {
+    /// Connect to a server whose certificate must be valid for
+    /// `server_name`, waiting at most `timeout` for the connection and the
+    /// reads and writes following it. The handshake happens along with the
+    /// first message
+    pub fn connect(
+        addr: SocketAddr,
+        server_name: &str,
+        client_config: Arc<ClientConfig>,
+        timeout: Duration,
+    ) -> io::Result<TlsConnection> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let connection = ClientConnection::new(client_config, server_name)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_nodelay(true)?;
+        let stream = DeadlineStream {
+            stream,
+            deadline: Instant::now() + timeout,
+        };
+        Ok(TlsConnection {
+            stream: StreamOwned::new(connection, stream),
+        })
+    }
+
+    /// Give up on the following reads and writes at `deadline`, however
+    /// slowly the server sends or receives
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.stream.sock.deadline = deadline;
+    }
+
+    /// Send a message
+    pub fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        tcp::write_frame(&mut self.stream, message)
+    }
+
+    /// Read the next message sent by the server
+    pub fn receive(&mut self) -> io::Result<Vec<u8>> {
+        tcp::read_frame(&mut self.stream)
+    }
+
+    /// Send a query and wait for its response until `deadline`, skipping
+    /// responses to other queries
+    fn exchange(
+        &mut self,
+        query: &DnsMessage,
+        deadline: Instant,
+    ) -> Result<DnsMessage, ResolveError> {
+        self.set_deadline(deadline);
+        self.send(&query.to_be_bytes()?)?;
+        loop {
+            let response = DnsMessage::parse(&self.receive()?)?;
+            if is_response_to(&response, query) {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+impl TlsClient {
+    /// Create a client authenticating servers as configured
+    pub fn new(config: TlsConfig) -> Result<TlsClient, rustls::Error> {
+        let client_config = config.client_config()?;
+        Ok(TlsClient {
+            config,
+            client_config: Arc::new(client_config),
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Send a query to the address of a server, `server_name` standing for
+    /// it unless the configuration names it
+    fn exchange_at(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        query: &DnsMessage,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let deadline = Instant::now() + timeout;
+        let idle = self
+            .connections
+            .lock()
+            .unwrap()
+            .remove(&addr)
+            .filter(|(_, last_used)| last_used.elapsed() < self.config.idle_timeout)
+            .map(|(connection, _)| connection);
+
+        let reused = idle.is_some();
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => self.connect(addr, server_name, timeout)?,
+        };
+        let response = match connection.exchange(query, deadline) {
+            Err(ResolveError::Network(_)) if reused => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(ResolveError::Timeout);
+                }
+                connection = self.connect(addr, server_name, remaining)?;
+                connection.exchange(query, deadline)?
+            }
+            result => result?,
+        };
+
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(addr, (connection, Instant::now()));
+        Ok(response)
+    }
+
+    fn connect(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Duration,
+    ) -> io::Result<TlsConnection> {
+        let server_name = self.config.server_name.as_deref().unwrap_or(server_name);
+        TlsConnection::connect(addr, server_name, self.client_config.clone(), timeout)
+    }
+}
+
+impl Transport for TlsClient {
+    /// Send a query to a server and wait at most `timeout` for its response.
+    /// A connection left open by an earlier query is used when there is one,
+    /// and replaced by a new one if the server has closed it meanwhile
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let addr = server.socket_addr(DOT_PORT)?;
+        self.exchange_at(addr, &server.host, query, timeout)
+    }
+}
+
+/// Verifier accepting the servers whose key matches a pin, whatever their
+/// certificate
+#[derive(Debug)]
+struct PinnedKeyVerifier {
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let spki = subject_public_key_info(end_entity).ok_or(rustls::Error::InvalidCertificate(
+            CertificateError::BadEncoding,
+        ))?;
+        let pin = spki_pin(&spki);
+        if self.pins.contains(&pin) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// SHA-256 digest of a DER SubjectPublicKeyInfo, the form keys are pinned in
+pub fn spki_pin(spki: &[u8]) -> [u8; 32] {
+    let mut pin = [0; 32];
+    pin.copy_from_slice(digest::digest(&digest::SHA256, spki).as_ref());
+    pin
+}
+
+/// SubjectPublicKeyInfo of a DER certificate (RFC 5280 section 4.1), tag
+/// and length included
+fn subject_public_key_info(certificate: &CertificateDer<'_>) -> Option<Vec<u8>> {
+    let certificate = webpki::EndEntityCert::try_from(certificate).ok()?;
+    Some(certificate.subject_public_key_info().as_ref().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::name::Name;
+    use rcgen::{CertifiedKey, PublicKeyData};
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// DNS over TLS server answering every query with an empty response,
+    /// counting the connections it accepts. A query with ID 0xFFFF is only
+    /// answered with responses to other queries, until the client hangs up,
+    /// one with ID 0xFFFE byte by byte, and one with ID 0xFFFD first with a
+    /// response to another question
+    struct StubServer {
+        addr: SocketAddr,
+        certificate: CertificateDer<'static>,
+        spki: Vec<u8>,
+        connections: Arc<AtomicUsize>,
+    }
+
+    impl StubServer {
+        fn start() -> StubServer {
+            let CertifiedKey { cert, signing_key } =
+                rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(signing_key.serialize_der()));
+            let provider = Arc::new(crypto::ring::default_provider());
+            let server_config = ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![cert.der().clone()], key)
+                .unwrap();
+            let server_config = Arc::new(server_config);
+
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let connections = Arc::new(AtomicUsize::new(0));
+            let accepted = connections.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    let connection = ServerConnection::new(server_config.clone()).unwrap();
+                    let mut stream = StreamOwned::new(connection, stream.unwrap());
+                    thread::spawn(move || {
+                        while let Ok(query) = tcp::read_frame(&mut stream) {
+                            let mut response = DnsMessage::parse(&query).unwrap();
+                            response.header.flags.set_qr(true);
+                            if response.header.id == 0xFFFF {
+                                response.header.id = 0;
+                                let other = response.to_be_bytes().unwrap();
+                                while tcp::write_frame(&mut stream, &other).is_ok() {
+                                    thread::sleep(Duration::from_millis(20));
+                                }
+                                return;
+                            }
+                            if response.header.id == 0xFFFE {
+                                let mut frame = vec![];
+                                tcp::write_frame(&mut frame, &response.to_be_bytes().unwrap())
+                                    .unwrap();
+                                for byte in frame {
+                                    if stream.write_all(&[byte]).is_err() {
+                                        return;
+                                    }
+                                    thread::sleep(Duration::from_millis(50));
+                                }
+                                continue;
+                            }
+                            if response.header.id == 0xFFFD {
+                                let mut other = DnsMessage::query("example.org".parse().unwrap())
+                                    .id(response.header.id)
+                                    .build();
+                                other.header.flags.set_qr(true);
+                                tcp::write_frame(&mut stream, &other.to_be_bytes().unwrap())
+                                    .unwrap();
+                            }
+                            tcp::write_frame(&mut stream, &response.to_be_bytes().unwrap())
+                                .unwrap();
+                        }
+                    });
+                }
+            });
+
+            StubServer {
+                addr,
+                certificate: cert.der().clone(),
+                spki: signing_key.subject_public_key_info(),
+                connections,
+            }
+        }
+
+        fn connections(&self) -> usize {
+            self.connections.load(Ordering::SeqCst)
+        }
+    }
+
+    fn query(id: u16) -> DnsMessage {
+        let name: Name = "example.com".parse().unwrap();
+        DnsMessage::query(name).id(id).build()
+    }
+
+    fn exchange(client: &TlsClient, server: &StubServer, id: u16) -> Result<u16, ResolveError> {
+        let timeout = Duration::from_secs(5);
+        let response = client.exchange(&query(id), &Server::from(server.addr), timeout)?;
+        assert!(response.header.flags.qr());
+        Ok(response.header.id)
+    }
+
+    #[test]
+    fn reuse_connection_to_trusted_server() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("localhost".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+
+        assert_eq!(exchange(&client, &server, 1).unwrap(), 1);
+        assert_eq!(exchange(&client, &server, 2).unwrap(), 2);
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[test]
+    fn reconnect_after_idle_timeout() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("localhost".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            idle_timeout: Duration::ZERO,
+            ..TlsConfig::default()
+        })
+        .unwrap();
+
+        exchange(&client, &server, 1).unwrap();
+        exchange(&client, &server, 2).unwrap();
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[test]
+    fn time_out_on_responses_to_other_queries() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("localhost".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+
+        let sent = Instant::now();
+        let timeout = Duration::from_millis(300);
+        let result = client.exchange(&query(0xFFFF), &Server::from(server.addr), timeout);
+        assert!(matches!(result, Err(ResolveError::Timeout)));
+        assert!(sent.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn time_out_on_trickling_response() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("localhost".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+
+        let sent = Instant::now();
+        let timeout = Duration::from_millis(300);
+        let result = client.exchange(&query(0xFFFE), &Server::from(server.addr), timeout);
+        assert!(matches!(result, Err(ResolveError::Timeout)));
+        assert!(sent.elapsed() < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn skip_responses_to_other_questions() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("localhost".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let response = client
+            .exchange(&query(0xFFFD), &Server::from(server.addr), timeout)
+            .unwrap();
+        assert_eq!(response.question(), query(0xFFFD).question());
+    }
+
+    #[test]
+    fn reject_certificate_for_another_name() {
+        let server = StubServer::start();
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("dns.example.com".to_string()),
+            root_certificates: vec![server.certificate.clone()],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            exchange(&client, &server, 1),
+            Err(ResolveError::Network(_))
+        ));
+    }
+
+    #[test]
+    fn authenticate_server_by_pinned_key() {
+        let server = StubServer::start();
+        let pin = spki_pin(&server.spki);
+        let client = TlsClient::new(TlsConfig {
+            server_name: Some("dns.example.com".to_string()),
+            spki_pins: vec![pin],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+        assert_eq!(exchange(&client, &server, 1).unwrap(), 1);
+
+        let client = TlsClient::new(TlsConfig {
+            spki_pins: vec![[0; 32]],
+            ..TlsConfig::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            exchange(&client, &server, 1),
+            Err(ResolveError::Network(_))
+        ));
+    }
+
+    #[test]
+    fn extract_subject_public_key_info() {
+        let CertifiedKey { cert, signing_key } =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        assert_eq!(
+            subject_public_key_info(cert.der()),
+            Some(signing_key.subject_public_key_info())
+        );
+        let truncated = CertificateDer::from(&cert.der()[..40]);
+        assert_eq!(subject_public_key_info(&truncated), None);
+    }
+}
@@ -0,0 +1,294 @@
+//! Transports carrying queries to DNS servers and their responses back
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::ResolveError;
+use crate::message::DnsMessage;
+use crate::name::Name;
+use crate::types::RecordType;
+
+/// Port DNS servers listen on over UDP and TCP
+pub const DNS_PORT: u16 = 53;
+
+/// DNS server a query is sent to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Server {
+    /// Name or address of the server, which its certificate is checked
+    /// against by encrypted transports
+    pub host: String,
+    /// Port of the server, the default port of the transport when `None`
+    pub port: Option<u16>,
+    /// Address the server is reached at instead of the addresses of its
+    /// host, as given by the glue of a referral
+    pub addr: Option<IpAddr>,
+}
+
+impl Server {
+    /// Server reached on the default port of the transport
+    pub fn new(host: impl Into<String>) -> Server {
+        Server {
+            host: host.into(),
+            port: None,
+            addr: None,
+        }
+    }
+
+    /// Server named `host`, which encrypted transports check its
+    /// certificate against, reached at a known address
+    pub fn at(host: impl Into<String>, addr: IpAddr) -> Server {
+        Server {
+            addr: Some(addr),
+            ..Server::new(host)
+        }
+    }
+
+    /// First address of the server, on `default_port` unless it has a port
+    pub fn socket_addr(&self, default_port: u16) -> io::Result<SocketAddr> {
+        let port = self.port.unwrap_or(default_port);
+        if let Some(addr) = self.addr {
+            return Ok(SocketAddr::new(addr, port));
+        }
+        (self.host.as_str(), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no address for {}", self.host),
+                )
+            })
+    }
+}
+
+impl From<SocketAddr> for Server {
+    fn from(addr: SocketAddr) -> Self {
+        Server {
+            host: addr.ip().to_string(),
+            port: Some(addr.port()),
+            addr: None,
+        }
+    }
+}
+
+impl FromStr for Server {
+    type Err = String;
+
+    /// Parse `host`, `host:port` or `[address]:port` for IPv6 addresses,
+    /// which may also be given alone without brackets
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty server".to_string());
+        }
+        if s.parse::<Ipv6Addr>().is_ok() {
+            return Ok(Server::new(s));
+        }
+        let (host, port) = match s.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|err| format!("{}: {}", port, err))?;
+                (host, Some(port))
+            }
+            None => (s, None),
+        };
+        let host = match host.strip_prefix('[') {
+            Some(address) => address
+                .strip_suffix(']')
+                .filter(|address| address.parse::<Ipv6Addr>().is_ok())
+                .ok_or_else(|| format!("Invalid IPv6 address: {}", host))?,
+            None => host,
+        };
+        Ok(Server {
+            host: host.to_string(),
+            port,
+            addr: None,
+        })
+    }
+}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) if self.host.contains(':') => write!(f, "[{}]:{}", self.host, port),
+            Some(port) => write!(f, "{}:{}", self.host, port),
+            None => write!(f, "{}", self.host),
+        }?;
+        match self.addr {
+            Some(addr) => write!(f, " ({})", addr),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Way of sending queries to DNS servers and receiving their responses
+pub trait Transport {
+    /// Send a query to a server and wait at most `timeout` for its response
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError>;
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        (**self).exchange(query, server, timeout)
+    }
+}
+
+/// Whether a message is the response to a query, carrying its ID and question
+pub(crate) fn is_response_to(response: &DnsMessage, query: &DnsMessage) -> bool {
+    response.header.id == query.header.id && response.question() == query.question()
+}
+
+/// Server and question a mock response answers
+type MockKey = (String, Name, RecordType);
+
+/// Transport answering queries from responses held in memory, to test how
+/// they are resolved without a network
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    /// Responses by the server and the question they answer
+    responses: Mutex<HashMap<MockKey, Result<DnsMessage, io::ErrorKind>>>,
+    /// Queries sent, in order, along with the server they were sent to
+    queries: Mutex<Vec<(Server, DnsMessage)>>,
+}
+
+impl MockTransport {
+    /// Create a transport without any response, where every query times out
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Answer the queries sent to `host`, or to the address of a server
+    /// reached at one, for the question of `response`
+    /// with it, the ID of each query copied over
+    pub fn add_response(&self, host: &str, response: DnsMessage) {
+        let key = Self::key(host, &response);
+        self.responses.lock().unwrap().insert(key, Ok(response));
+    }
+
+    /// Fail the queries sent to `host` for the question of `query` with an
+    /// I/O error of some kind
+    pub fn add_error(&self, host: &str, query: &DnsMessage, kind: io::ErrorKind) {
+        let key = Self::key(host, query);
+        self.responses.lock().unwrap().insert(key, Err(kind));
+    }
+
+    /// Queries sent so far along with the server each was sent to
+    pub fn queries(&self) -> Vec<(Server, DnsMessage)> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    fn key(host: &str, message: &DnsMessage) -> MockKey {
+        match message.question() {
+            Some(question) => (host.to_string(), question.q_name.clone(), question.q_type),
+            None => (host.to_string(), Name::root(), RecordType::ANY),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        _timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        self.queries
+            .lock()
+            .unwrap()
+            .push((server.clone(), query.clone()));
+        let responses = self.responses.lock().unwrap();
+        let host = server
+            .addr
+            .map_or_else(|| server.host.clone(), |addr| addr.to_string());
+        match responses.get(&Self::key(&host, query)) {
+            Some(Ok(response)) => {
+                let mut response = response.clone();
+                response.header.id = query.header.id;
+                Ok(response)
+            }
+            Some(Err(kind)) => Err(io::Error::from(*kind).into()),
+            None => Err(ResolveError::Timeout),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_servers() {
+        let server = |host: &str, port| Server {
+            host: host.to_string(),
+            port,
+            addr: None,
+        };
+        for (text, expected) in [
+            ("192.0.2.1", server("192.0.2.1", None)),
+            ("192.0.2.1:5353", server("192.0.2.1", Some(5353))),
+            ("dns.example.com", server("dns.example.com", None)),
+            ("2001:db8::1", server("2001:db8::1", None)),
+            ("[2001:db8::1]:853", server("2001:db8::1", Some(853))),
+        ] {
+            assert_eq!(text.parse::<Server>(), Ok(expected.clone()));
+            assert_eq!(expected.to_string(), text);
+        }
+        let glued = Server::at("ns.example.com", "2001:db8::53".parse().unwrap());
+        assert_eq!(glued.to_string(), "ns.example.com (2001:db8::53)");
+        assert_eq!(
+            glued.socket_addr(853).unwrap().to_string(),
+            "[2001:db8::53]:853"
+        );
+        assert!("".parse::<Server>().is_err());
+        assert!("192.0.2.1:dns".parse::<Server>().is_err());
+        assert!("[dns.example.com]:53".parse::<Server>().is_err());
+    }
+
+    #[test]
+    fn answer_from_memory() {
+        let transport = MockTransport::new();
+        let server = Server::new("192.0.2.1");
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(7)
+            .build();
+        let mut response = query.clone();
+        response.header.id = 0;
+        response.header.flags.set_qr(true);
+        transport.add_response("192.0.2.1", response);
+        transport.add_error("192.0.2.2", &query, io::ErrorKind::ConnectionRefused);
+
+        let timeout = Duration::from_secs(1);
+        let answer = transport.exchange(&query, &server, timeout).unwrap();
+        assert_eq!(answer.header.id, 7);
+        assert!(answer.header.flags.qr());
+        assert!(matches!(
+            transport.exchange(&query, &Server::new("192.0.2.2"), timeout),
+            Err(ResolveError::Network(err)) if err.kind() == io::ErrorKind::ConnectionRefused
+        ));
+        assert!(matches!(
+            transport.exchange(&query, &Server::new("192.0.2.3"), timeout),
+            Err(ResolveError::Timeout)
+        ));
+        let hosts: Vec<_> = transport
+            .queries()
+            .into_iter()
+            .map(|(server, _)| server.host)
+            .collect();
+        assert_eq!(hosts, ["192.0.2.1", "192.0.2.2", "192.0.2.3"]);
+    }
+}
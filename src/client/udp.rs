@@ -0,0 +1,278 @@
+//! DNS over UDP, falling back to TCP for truncated responses (RFC 7766)
+
+use spdlog::prelude::*;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::client::tcp::TcpTransport;
+use crate::client::transport::{is_response_to, Server, Transport, DNS_PORT};
+use crate::error::ResolveError;
+use crate::message::{DnsMessage, MAX_MESSAGE_SIZE};
+
+/// Transport sending queries over UDP from a bound socket per address
+/// family, and asking for truncated responses again over TCP
+#[derive(Debug)]
+pub struct UdpTransport {
+    ipv4_socket: UdpSocket,
+    /// Socket for IPv6 servers, `None` on hosts without IPv6
+    ipv6_socket: Option<UdpSocket>,
+}
+
+impl UdpTransport {
+    /// Bind the sockets to send queries from
+    pub fn new() -> io::Result<UdpTransport> {
+        let ipv4_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        debug!(
+            "Initialize host at address: {:#?}",
+            ipv4_socket.local_addr().unwrap()
+        );
+        let ipv6_socket = match UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)) {
+            Ok(socket) => Some(socket),
+            Err(err) => {
+                warn!("No IPv6 socket to query servers from: {}", err);
+                None
+            }
+        };
+        Ok(UdpTransport {
+            ipv4_socket,
+            ipv6_socket,
+        })
+    }
+
+    /// Socket bound to the address family of `addr`
+    fn socket(&self, addr: SocketAddr) -> io::Result<&UdpSocket> {
+        match addr {
+            SocketAddr::V4(_) => Ok(&self.ipv4_socket),
+            SocketAddr::V6(_) => self.ipv6_socket.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    format!("no IPv6 socket to reach {}", addr),
+                )
+            }),
+        }
+    }
+
+    /// Send a query over UDP and wait for its response, skipping late
+    /// responses to earlier queries, datagrams answering another question and
+    /// malformed ones
+    fn exchange_udp(
+        &self,
+        addr: SocketAddr,
+        query: &DnsMessage,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let deadline = Instant::now() + timeout;
+        let socket = self.socket(addr)?;
+        debug!("Connecting to {}", addr);
+        socket.connect(addr)?;
+        Self::send(socket, addr, &query.to_be_bytes()?)?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(ResolveError::Timeout);
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let bytes = Self::listen(socket, query.max_response_size())?;
+            // Anyone may send a malformed datagram, which leaves the genuine
+            // response to wait for
+            let Ok(response) = parse_response(addr, &bytes) else {
+                continue;
+            };
+            if is_response_to(&response, query) {
+                return Ok(response);
+            }
+            debug!(
+                "Skipping response {} from {} to another query",
+                response.header.id, addr
+            );
+        }
+    }
+
+    /// Send a udp message to a remote address
+    fn send(socket: &UdpSocket, addr: SocketAddr, msg: &[u8]) -> io::Result<usize> {
+        match socket.send(msg) {
+            Ok(number_of_bytes) => {
+                debug!(
+                    "Send a {}-byte message to address: {}",
+                    number_of_bytes, addr
+                );
+                Ok(number_of_bytes)
+            }
+            Err(err) => {
+                error!("Failed sending message: {:02x?}: {}", msg, err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Listen to a response of at most `max_size` bytes from the connected
+    /// address
+    fn listen(socket: &UdpSocket, max_size: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0; max_size.min(MAX_MESSAGE_SIZE)];
+        let number_of_bytes = socket.recv(&mut buffer)?;
+        debug!("Received: {} bytes", number_of_bytes);
+        buffer.truncate(number_of_bytes);
+        Ok(buffer)
+    }
+}
+
+impl Transport for UdpTransport {
+    fn exchange(
+        &self,
+        query: &DnsMessage,
+        server: &Server,
+        timeout: Duration,
+    ) -> Result<DnsMessage, ResolveError> {
+        let sent = Instant::now();
+        let addr = server.socket_addr(DNS_PORT)?;
+        let response = self.exchange_udp(addr, query, timeout)?;
+        if !response.header.flags.tc() {
+            return Ok(response);
+        }
+
+        info!("Truncated response from {}, retrying over TCP", server);
+        let remaining = timeout.saturating_sub(sent.elapsed());
+        if remaining.is_zero() {
+            return Err(ResolveError::Timeout);
+        }
+        TcpTransport.exchange(query, server, remaining)
+    }
+}
+
+/// Parse the response of a DNS server
+pub(crate) fn parse_response(addr: SocketAddr, bytes: &[u8]) -> Result<DnsMessage, ResolveError> {
+    DnsMessage::parse(bytes).map_err(|err| {
+        error!("Malformed response from {}: {}", addr, err);
+        err.into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tcp;
+    use std::net::{TcpListener, UdpSocket};
+    use std::thread;
+
+    #[test]
+    fn retry_truncated_response_over_tcp() {
+        let udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(addr).unwrap();
+        let server = thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, client) = udp.recv_from(&mut buffer).unwrap();
+            let mut truncated = DnsMessage::parse(&buffer[..len]).unwrap();
+            truncated.header.flags.set_qr(true);
+            truncated.header.flags.set_tc(true);
+            udp.send_to(&truncated.to_be_bytes().unwrap(), client)
+                .unwrap();
+
+            let (mut stream, _) = tcp.accept().unwrap();
+            let mut response = DnsMessage::parse(&tcp::read_frame(&mut stream).unwrap()).unwrap();
+            response.header.flags.set_qr(true);
+            tcp::write_frame(&mut stream, &response.to_be_bytes().unwrap()).unwrap();
+        });
+
+        let transport = UdpTransport::new().unwrap();
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let response = transport
+            .exchange(&query, &Server::from(addr), Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(response.header.id, 9);
+        assert!(response.header.flags.qr());
+        assert!(!response.header.flags.tc());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn query_ipv6_server() {
+        let Ok(udp) = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)) else {
+            return;
+        };
+        let addr = udp.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, client) = udp.recv_from(&mut buffer).unwrap();
+            let mut response = DnsMessage::parse(&buffer[..len]).unwrap();
+            response.header.flags.set_qr(true);
+            udp.send_to(&response.to_be_bytes().unwrap(), client)
+                .unwrap();
+        });
+
+        let transport = UdpTransport::new().unwrap();
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let response = transport
+            .exchange(&query, &Server::from(addr), Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(response.header.id, 9);
+        assert!(response.header.flags.qr());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn skip_stale_and_malformed_responses() {
+        let udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = udp.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, client) = udp.recv_from(&mut buffer).unwrap();
+            let query = DnsMessage::parse(&buffer[..len]).unwrap();
+
+            udp.send_to(&[0xFF; 5], client).unwrap();
+            let mut stale = query.clone();
+            stale.header.id = query.header.id.wrapping_sub(1);
+            stale.header.flags.set_qr(true);
+            udp.send_to(&stale.to_be_bytes().unwrap(), client).unwrap();
+            let mut other = DnsMessage::query("example.org".parse().unwrap())
+                .id(query.header.id)
+                .build();
+            other.header.flags.set_qr(true);
+            udp.send_to(&other.to_be_bytes().unwrap(), client).unwrap();
+            let mut response = query;
+            response.header.flags.set_qr(true);
+            response.header.flags.set_aa(true);
+            udp.send_to(&response.to_be_bytes().unwrap(), client)
+                .unwrap();
+        });
+
+        let transport = UdpTransport::new().unwrap();
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let response = transport
+            .exchange(&query, &Server::from(addr), Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(response.header.id, 9);
+        assert_eq!(response.question(), query.question());
+        assert!(response.header.flags.aa());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn time_out_on_mismatched_responses() {
+        let udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = udp.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, client) = udp.recv_from(&mut buffer).unwrap();
+            let mut stale = DnsMessage::parse(&buffer[..len]).unwrap();
+            stale.header.id = stale.header.id.wrapping_add(1);
+            stale.header.flags.set_qr(true);
+            udp.send_to(&stale.to_be_bytes().unwrap(), client).unwrap();
+        });
+
+        let transport = UdpTransport::new().unwrap();
+        let query = DnsMessage::query("example.com".parse().unwrap())
+            .id(9)
+            .build();
+        let result = transport.exchange(&query, &Server::from(addr), Duration::from_millis(200));
+        assert!(matches!(result, Err(ResolveError::Timeout)));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,513 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::encoder::Encoder;
+use crate::error::ParseError;
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::{Class, RecordType};
+use crate::utility::{self, hex};
+
+/// Payload size advertised by default, small enough to avoid IP
+/// fragmentation on common paths (DNS flag day 2020)
+pub const DEFAULT_UDP_PAYLOAD_SIZE: u16 = 1232;
+
+/// EDNS(0) information carried by the OPT pseudo-record (RFC 6891)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// Largest UDP payload the sender is able to receive
+    pub udp_payload_size: u16,
+    /// Upper eight bits of the twelve-bit response code
+    pub extended_rcode: u8,
+    /// EDNS version, 0 is the only one defined
+    pub version: u8,
+    /// DNSSEC OK - the sender is able to accept DNSSEC records
+    pub dnssec_ok: bool,
+    /// Remaining flag bits, reserved and zero
+    pub z: u16,
+    /// EDNS options
+    pub options: Vec<EdnsOption>,
+}
+
+/// Option carried in the rdata of an OPT pseudo-record. Options whose data
+/// does not match the format of their code are kept as `Unknown`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdnsOption {
+    /// Name server identifier (RFC 5001), empty in queries
+    Nsid(Vec<u8>),
+    /// Client subnet the query originated from (RFC 7871)
+    ClientSubnet {
+        /// Number of significant bits of the address sent by the client
+        source_prefix: u8,
+        /// Number of significant bits the answer is valid for, zero in queries
+        scope_prefix: u8,
+        /// Address with all bits past the source prefix cleared
+        address: IpAddr,
+    },
+    /// DNS cookie (RFC 7873)
+    Cookie {
+        /// Client cookie
+        client: [u8; 8],
+        /// Server cookie of 8 to 32 bytes, if known
+        server: Option<Vec<u8>>,
+    },
+    /// Number of zero bytes padding the message (RFC 7830)
+    Padding(u16),
+    /// Extended DNS error (RFC 8914)
+    ExtendedError {
+        /// Info code giving the reason of the error
+        info_code: u16,
+        /// Additional human-readable text
+        extra_text: String,
+    },
+    /// Option without a dedicated representation
+    Unknown {
+        /// Option code
+        code: u16,
+        /// Option data
+        data: Vec<u8>,
+    },
+}
+
+impl Default for Edns {
+    fn default() -> Self {
+        Edns {
+            udp_payload_size: DEFAULT_UDP_PAYLOAD_SIZE,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            z: 0,
+            options: vec![],
+        }
+    }
+}
+
+impl Edns {
+    /// Lift EDNS information out of an OPT record found at `offset`
+    pub fn parse(rr: &ResourceRecord, offset: usize) -> Result<Edns, ParseError> {
+        let invalid = ParseError::InvalidOpt { offset };
+        let RData::Unknown(data) = &rr.an_rdata else {
+            return Err(invalid);
+        };
+        if rr.an_type != RecordType::OPT || !rr.an_name.is_root() {
+            return Err(invalid);
+        }
+
+        let mut options = vec![];
+        let mut pos = 0;
+        while pos < data.len() {
+            let code = utility::read_u16(data, pos).map_err(|_| invalid.clone())?;
+            let len = utility::read_u16(data, pos + 2).map_err(|_| invalid.clone())? as usize;
+            let data = data.get(pos + 4..pos + 4 + len).ok_or(invalid.clone())?;
+            options.push(EdnsOption::parse(code, data));
+            pos += 4 + len;
+        }
+
+        Ok(Edns {
+            udp_payload_size: u16::from(rr.an_class),
+            extended_rcode: (rr.an_ttl >> 24) as u8,
+            version: (rr.an_ttl >> 16) as u8,
+            dnssec_ok: rr.an_ttl & 0x8000 != 0,
+            z: (rr.an_ttl & 0x7FFF) as u16,
+            options,
+        })
+    }
+
+    /// Transform into the OPT record placed in the additional section
+    pub fn to_record(&self) -> ResourceRecord {
+        let mut encoder = Encoder::new(false);
+        for option in &self.options {
+            option.encode(&mut encoder);
+        }
+
+        ResourceRecord {
+            an_name: Name::root(),
+            an_type: RecordType::OPT,
+            an_class: Class::from(self.udp_payload_size),
+            an_ttl: (self.extended_rcode as u32) << 24
+                | (self.version as u32) << 16
+                | (self.dnssec_ok as u32) << 15
+                | (self.z & 0x7FFF) as u32,
+            an_rdata: RData::Unknown(encoder.into_bytes()),
+        }
+    }
+}
+
+impl EdnsOption {
+    pub const NSID: u16 = 3;
+    pub const CLIENT_SUBNET: u16 = 8;
+    pub const COOKIE: u16 = 10;
+    pub const PADDING: u16 = 12;
+    pub const EXTENDED_ERROR: u16 = 15;
+
+    /// Client subnet option for a query, clearing the address bits past the
+    /// prefix as RFC 7871 requires
+    pub fn client_subnet(address: IpAddr, source_prefix: u8) -> EdnsOption {
+        let address = match address {
+            IpAddr::V4(addr) => {
+                let prefix = source_prefix.min(32) as u32;
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(addr) & mask))
+            }
+            IpAddr::V6(addr) => {
+                let prefix = source_prefix.min(128) as u32;
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(addr) & mask))
+            }
+        };
+        EdnsOption::ClientSubnet {
+            source_prefix,
+            scope_prefix: 0,
+            address,
+        }
+    }
+
+    /// Option code
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Nsid(_) => EdnsOption::NSID,
+            EdnsOption::ClientSubnet { .. } => EdnsOption::CLIENT_SUBNET,
+            EdnsOption::Cookie { .. } => EdnsOption::COOKIE,
+            EdnsOption::Padding(_) => EdnsOption::PADDING,
+            EdnsOption::ExtendedError { .. } => EdnsOption::EXTENDED_ERROR,
+            EdnsOption::Unknown { code, .. } => *code,
+        }
+    }
+
+    /// Parse the data of an option with the given code
+    pub fn parse(code: u16, data: &[u8]) -> EdnsOption {
+        let option = match code {
+            EdnsOption::NSID => Some(EdnsOption::Nsid(data.to_vec())),
+            EdnsOption::CLIENT_SUBNET => EdnsOption::parse_client_subnet(data),
+            EdnsOption::COOKIE => match data.len() {
+                8 | 16..=40 => Some(EdnsOption::Cookie {
+                    client: data[..8].try_into().unwrap_or_default(),
+                    server: (data.len() > 8).then(|| data[8..].to_vec()),
+                }),
+                _ => None,
+            },
+            EdnsOption::PADDING if data.iter().all(|&b| b == 0) => {
+                Some(EdnsOption::Padding(data.len() as u16))
+            }
+            EdnsOption::EXTENDED_ERROR if data.len() >= 2 => {
+                let extra_text = String::from_utf8(data[2..].to_vec()).ok();
+                extra_text.map(|extra_text| EdnsOption::ExtendedError {
+                    info_code: u16::from_be_bytes([data[0], data[1]]),
+                    extra_text,
+                })
+            }
+            _ => None,
+        };
+
+        option.unwrap_or_else(|| EdnsOption::Unknown {
+            code,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Parse client subnet data, only accepting the canonical form where the
+    /// address is as short as the prefix allows and its extra bits are zero
+    fn parse_client_subnet(data: &[u8]) -> Option<EdnsOption> {
+        let family = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+        let source_prefix = *data.get(2)?;
+        let scope_prefix = *data.get(3)?;
+        let bytes = &data[4..];
+        if bytes.len() != (source_prefix as usize).div_ceil(8) {
+            return None;
+        }
+
+        let address = match family {
+            1 if source_prefix <= 32 => {
+                let mut octets = [0; 4];
+                octets[..bytes.len()].copy_from_slice(bytes);
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            2 if source_prefix <= 128 => {
+                let mut octets = [0; 16];
+                octets[..bytes.len()].copy_from_slice(bytes);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return None,
+        };
+
+        let option = EdnsOption::client_subnet(address, source_prefix);
+        let EdnsOption::ClientSubnet {
+            address: masked, ..
+        } = option
+        else {
+            return None;
+        };
+        (masked == address).then_some(EdnsOption::ClientSubnet {
+            source_prefix,
+            scope_prefix,
+            address,
+        })
+    }
+
+    /// Write the option code, length and data into an encoder
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_u16(self.code());
+        let len_pos = encoder.position();
+        encoder.put_u16(0);
+
+        match self {
+            EdnsOption::Nsid(data) => encoder.put_bytes(data),
+            EdnsOption::ClientSubnet {
+                source_prefix,
+                scope_prefix,
+                address,
+            } => {
+                let len = (*source_prefix as usize).div_ceil(8);
+                let (family, octets) = match address {
+                    IpAddr::V4(addr) => (1, addr.octets().to_vec()),
+                    IpAddr::V6(addr) => (2, addr.octets().to_vec()),
+                };
+                encoder.put_u16(family);
+                encoder.put_u8(*source_prefix);
+                encoder.put_u8(*scope_prefix);
+                encoder.put_bytes(&octets[..len.min(octets.len())]);
+            }
+            EdnsOption::Cookie { client, server } => {
+                encoder.put_bytes(client);
+                if let Some(server) = server {
+                    encoder.put_bytes(server);
+                }
+            }
+            EdnsOption::Padding(len) => encoder.put_bytes(&vec![0; *len as usize]),
+            EdnsOption::ExtendedError {
+                info_code,
+                extra_text,
+            } => {
+                encoder.put_u16(*info_code);
+                encoder.put_bytes(extra_text.as_bytes());
+            }
+            EdnsOption::Unknown { data, .. } => encoder.put_bytes(data),
+        }
+
+        let len = encoder.position() - len_pos - 2;
+        encoder.set_u16(len_pos, len as u16);
+    }
+}
+
+/// Meaning of an extended DNS error info code (RFC 8914)
+pub fn extended_error_name(info_code: u16) -> Option<&'static str> {
+    let name = match info_code {
+        0 => "Other Error",
+        1 => "Unsupported DNSKEY Algorithm",
+        2 => "Unsupported DS Digest Type",
+        3 => "Stale Answer",
+        4 => "Forged Answer",
+        5 => "DNSSEC Indeterminate",
+        6 => "DNSSEC Bogus",
+        7 => "Signature Expired",
+        8 => "Signature Not Yet Valid",
+        9 => "DNSKEY Missing",
+        10 => "RRSIGs Missing",
+        11 => "No Zone Key Bit Set",
+        12 => "NSEC Missing",
+        13 => "Cached Error",
+        14 => "Not Ready",
+        15 => "Blocked",
+        16 => "Censored",
+        17 => "Filtered",
+        18 => "Prohibited",
+        19 => "Stale NXDOMAIN Answer",
+        20 => "Not Authoritative",
+        21 => "Not Supported",
+        22 => "No Reachable Authority",
+        23 => "Network Error",
+        24 => "Invalid Data",
+        _ => return None,
+    };
+    Some(name)
+}
+
+impl fmt::Display for Edns {
+    /// Write the EDNS information as the comment lines of the OPT
+    /// pseudo-section shown by dig
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "; EDNS: version: {}, flags:", self.version)?;
+        if self.dnssec_ok {
+            write!(f, " do")?;
+        }
+        if self.z != 0 {
+            write!(f, "; MBZ: {:#06x}", self.z)?;
+        }
+        write!(f, "; udp: {}", self.udp_payload_size)?;
+        for option in &self.options {
+            write!(f, "\n; {}", option)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdnsOption::Nsid(data) => {
+                write!(f, "NSID: {}", hex(data))?;
+                if !data.is_empty() && data.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+                    write!(f, " (\"{}\")", String::from_utf8_lossy(data))?;
+                }
+                Ok(())
+            }
+            EdnsOption::ClientSubnet {
+                source_prefix,
+                scope_prefix,
+                address,
+            } => write!(
+                f,
+                "CLIENT-SUBNET: {}/{}/{}",
+                address, source_prefix, scope_prefix
+            ),
+            EdnsOption::Cookie { client, server } => {
+                write!(f, "COOKIE: {}", hex(client))?;
+                if let Some(server) = server {
+                    write!(f, "{}", hex(server))?;
+                }
+                Ok(())
+            }
+            EdnsOption::Padding(len) => write!(f, "PADDING: {} bytes", len),
+            EdnsOption::ExtendedError {
+                info_code,
+                extra_text,
+            } => {
+                write!(f, "EDE: {}", info_code)?;
+                if let Some(name) = extended_error_name(*info_code) {
+                    write!(f, " ({})", name)?;
+                }
+                if !extra_text.is_empty() {
+                    write!(f, ": {}", extra_text)?;
+                }
+                Ok(())
+            }
+            EdnsOption::Unknown { code, data } => write!(f, "OPT{}: {}", code, hex(data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+
+    #[test]
+    fn round_trip_opt_record() {
+        let edns = Edns {
+            udp_payload_size: 4096,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            z: 0,
+            options: vec![EdnsOption::Unknown {
+                code: 65001,
+                data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            }],
+        };
+
+        let rr = edns.to_record();
+        assert_eq!(rr.an_ttl, 0x0100_8000);
+        assert_eq!(
+            rr.to_be_bytes().unwrap(),
+            [0, 0, 41, 16, 0, 1, 0, 128, 0, 0, 12, 0xFD, 0xE9, 0, 8, 1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(Edns::parse(&rr, 0), Ok(edns));
+    }
+
+    #[test]
+    fn parse_client_subnet() {
+        let option = EdnsOption::client_subnet("192.0.2.77".parse().unwrap(), 24);
+        let mut encoder = Encoder::new(false);
+        option.encode(&mut encoder);
+        let bytes = encoder.into_bytes();
+        assert_eq!(bytes, [0, 8, 0, 7, 0, 1, 24, 0, 192, 0, 2]);
+        assert_eq!(EdnsOption::parse(8, &bytes[4..]), option);
+        assert_eq!(option.to_string(), "CLIENT-SUBNET: 192.0.2.0/24/0");
+
+        let option = EdnsOption::parse(8, &[0, 2, 56, 48, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0]);
+        assert_eq!(option.to_string(), "CLIENT-SUBNET: 2001:db8::/56/48");
+    }
+
+    #[test]
+    fn keep_non_canonical_client_subnet() {
+        // Address bits past the source prefix must be zero
+        let data = [0, 1, 20, 0, 192, 0, 2];
+        assert_eq!(
+            EdnsOption::parse(8, &data),
+            EdnsOption::Unknown {
+                code: 8,
+                data: data.to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_cookie() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let option = EdnsOption::parse(10, &data);
+        assert_eq!(
+            option,
+            EdnsOption::Cookie {
+                client: [1, 2, 3, 4, 5, 6, 7, 8],
+                server: Some(vec![9, 10, 11, 12, 13, 14, 15, 16]),
+            }
+        );
+        assert_eq!(
+            option.to_string(),
+            "COOKIE: 0102030405060708090a0b0c0d0e0f10"
+        );
+        // Server cookies are between 8 and 32 bytes long
+        assert!(matches!(
+            EdnsOption::parse(10, &data[..12]),
+            EdnsOption::Unknown { .. }
+        ));
+    }
+
+    #[test]
+    fn parse_nsid_padding_and_extended_error() {
+        let option = EdnsOption::parse(3, b"ns1");
+        assert_eq!(option.to_string(), "NSID: 6e7331 (\"ns1\")");
+        assert_eq!(EdnsOption::parse(12, &[0; 5]), EdnsOption::Padding(5));
+
+        let mut data = vec![0, 18];
+        data.extend_from_slice(b"blocked by policy");
+        let option = EdnsOption::parse(15, &data);
+        assert_eq!(
+            option,
+            EdnsOption::ExtendedError {
+                info_code: 18,
+                extra_text: "blocked by policy".to_string(),
+            }
+        );
+        assert_eq!(
+            option.to_string(),
+            "EDE: 18 (Prohibited): blocked by policy"
+        );
+    }
+
+    #[test]
+    fn reject_truncated_option() {
+        let mut rr = Edns::default().to_record();
+        rr.an_rdata = RData::Unknown(vec![0, 10, 0, 8, 1, 2]);
+        assert_eq!(
+            Edns::parse(&rr, 40),
+            Err(ParseError::InvalidOpt { offset: 40 })
+        );
+    }
+
+    #[test]
+    fn reject_non_root_owner() {
+        let mut rr = Edns::default().to_record();
+        rr.an_name = "a".parse().unwrap();
+        assert!(Edns::parse(&rr, 0).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn edns_round_trip(edns in strategies::edns()) {
+            prop_assert_eq!(Edns::parse(&edns.to_record(), 0), Ok(edns));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::name::Name;
+
+/// Largest offset that can be referenced by a compression pointer
+const MAX_POINTER_OFFSET: usize = 0x3FFF;
+
+/// Writer for DNS wire format with optional name compression
+pub struct Encoder {
+    /// Bytes written so far
+    buf: Vec<u8>,
+    /// Offsets of every name suffix already written, keyed by its wire form
+    names: HashMap<Vec<u8>, u16>,
+    /// Whether repeated name suffixes are replaced by pointers
+    compress: bool,
+}
+
+impl Encoder {
+    /// Create a new encoder
+    pub fn new(compress: bool) -> Encoder {
+        Encoder {
+            buf: vec![],
+            names: HashMap::new(),
+            compress,
+        }
+    }
+
+    /// Number of bytes written so far
+    pub fn position(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Append a single byte
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    /// Append a two-octet number in network byte order
+    pub fn put_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Append a four-octet number in network byte order
+    pub fn put_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Append raw bytes
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Overwrite a two-octet number that was written earlier at `pos`
+    pub fn set_u16(&mut self, pos: usize, value: u16) {
+        self.buf[pos..pos + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Append a domain name, replacing the longest suffix written before
+    /// with a pointer when compression is on
+    pub fn put_name(&mut self, name: &Name) {
+        let name = name.as_wire();
+        let mut i = 0;
+        while name[i] != 0 {
+            let len = name[i] as usize;
+            let suffix = &name[i..];
+            if self.compress {
+                if let Some(&offset) = self.names.get(suffix) {
+                    self.put_u16(0xC000 | offset);
+                    return;
+                }
+            }
+            if self.position() <= MAX_POINTER_OFFSET {
+                self.names.insert(suffix.to_vec(), self.position() as u16);
+            }
+
+            self.put_bytes(&name[i..i + 1 + len]);
+            i += 1 + len;
+        }
+        self.put_u8(0);
+    }
+
+    /// Finish encoding and return the written bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_COM: [u8; 13] = [
+        7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+    ];
+
+    fn example_com() -> Name {
+        Name::from_wire(&EXAMPLE_COM).unwrap()
+    }
+
+    #[test]
+    fn compress_repeated_name() {
+        let mut enc = Encoder::new(true);
+        enc.put_bytes(&[0; 12]);
+        enc.put_name(&example_com());
+        enc.put_name(&example_com());
+        assert_eq!(enc.into_bytes()[25..], [0xC0, 12]);
+    }
+
+    #[test]
+    fn compress_repeated_suffix() {
+        let mut enc = Encoder::new(true);
+        enc.put_bytes(&[0; 12]);
+        enc.put_name(&example_com());
+        enc.put_name(&"www.com".parse().unwrap());
+        assert_eq!(enc.into_bytes()[25..], [3, b'w', b'w', b'w', 0xC0, 20]);
+    }
+
+    #[test]
+    fn keep_names_when_compression_is_off() {
+        let mut enc = Encoder::new(false);
+        enc.put_name(&example_com());
+        enc.put_name(&example_com());
+        assert_eq!(enc.into_bytes(), [EXAMPLE_COM, EXAMPLE_COM].concat());
+    }
+}
@@ -0,0 +1,377 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::header::Rcode;
+use crate::name::Name;
+use crate::types::RecordType;
+
+/// Error raised when bytes can't be parsed into a DNS message. Every variant
+/// carries the offset in the message at which the problem was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The message is shorter than the 12-byte header
+    TruncatedHeader { offset: usize },
+    /// A field runs past the end of the message
+    Truncated { offset: usize },
+    /// A label length octet uses one of the reserved label types
+    BadLabelLength { offset: usize, length: u8 },
+    /// A compression pointer points at or after itself
+    ForwardPointer { offset: usize },
+    /// A compression pointer leads back into a name already being decoded
+    PointerLoop { offset: usize },
+    /// A domain name is longer than 255 bytes
+    NameTooLong { offset: usize },
+    /// The rdlength of a resource record runs past the end of the message
+    RdLengthOverrun { offset: usize, rdlength: u16 },
+    /// The rdata does not match the format of its type
+    InvalidRData { offset: usize, rr_type: u16 },
+    /// An OPT pseudo-record is malformed, misplaced or repeated
+    InvalidOpt { offset: usize },
+    /// Bytes are left over after the last section
+    TrailingBytes { offset: usize },
+}
+
+impl ParseError {
+    /// Offset in the message at which the error was found
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::TruncatedHeader { offset }
+            | ParseError::Truncated { offset }
+            | ParseError::BadLabelLength { offset, .. }
+            | ParseError::ForwardPointer { offset }
+            | ParseError::PointerLoop { offset }
+            | ParseError::NameTooLong { offset }
+            | ParseError::RdLengthOverrun { offset, .. }
+            | ParseError::InvalidRData { offset, .. }
+            | ParseError::InvalidOpt { offset }
+            | ParseError::TrailingBytes { offset } => offset,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::TruncatedHeader { .. } => write!(f, "message is shorter than a header"),
+            ParseError::Truncated { offset } => write!(f, "message truncated at offset {}", offset),
+            ParseError::BadLabelLength { offset, length } => {
+                write!(f, "bad label length {:#04x} at offset {}", length, offset)
+            }
+            ParseError::ForwardPointer { offset } => {
+                write!(f, "forward compression pointer at offset {}", offset)
+            }
+            ParseError::PointerLoop { offset } => {
+                write!(f, "compression pointer loop at offset {}", offset)
+            }
+            ParseError::NameTooLong { offset } => {
+                write!(f, "domain name longer than 255 bytes at offset {}", offset)
+            }
+            ParseError::RdLengthOverrun { offset, rdlength } => write!(
+                f,
+                "rdlength {} at offset {} exceeds the message",
+                rdlength, offset
+            ),
+            ParseError::InvalidRData { offset, rr_type } => {
+                write!(f, "invalid rdata for type {} at offset {}", rr_type, offset)
+            }
+            ParseError::InvalidOpt { offset } => {
+                write!(f, "invalid OPT record at offset {}", offset)
+            }
+            ParseError::TrailingBytes { offset } => {
+                write!(f, "trailing bytes after the message at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Error raised when a message can't be written in wire format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A character string is longer than the 255 bytes its length octet
+    /// can count
+    StringTooLong { length: usize },
+    /// The rdata of a record is longer than the 65535 bytes its rdlength
+    /// can count
+    RDataTooLong { rr_type: u16, length: usize },
+    /// The rdata of a record is the representation of another type than
+    /// the type of the record
+    RDataMismatch { rr_type: u16, rdata_type: u16 },
+    /// A section of a message holds more than the 65535 entries its count
+    /// in the header can count
+    SectionTooLong { count: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::StringTooLong { length } => {
+                write!(f, "character string of {} bytes exceeds 255 bytes", length)
+            }
+            EncodeError::RDataTooLong { rr_type, length } => write!(
+                f,
+                "rdata of {} bytes for type {} exceeds 65535 bytes",
+                length, rr_type
+            ),
+            EncodeError::RDataMismatch {
+                rr_type,
+                rdata_type,
+            } => write!(
+                f,
+                "rdata of type {} in a record of type {}",
+                rdata_type, rr_type
+            ),
+            EncodeError::SectionTooLong { count } => {
+                write!(f, "section of {} entries exceeds 65535 entries", count)
+            }
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+/// Error raised when text can't be turned into a domain name. Positions are
+/// byte offsets in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// The text is empty, or a label between two dots is empty
+    EmptyLabel { position: usize },
+    /// A label is longer than 63 bytes
+    LabelTooLong { position: usize, length: usize },
+    /// The name is longer than 255 bytes in wire format
+    NameTooLong { length: usize },
+    /// A backslash is not followed by a character or by three digits
+    /// forming a value up to 255
+    InvalidEscape { position: usize },
+    /// A label written in Unicode holds a code point that IDNA disallows
+    DisallowedCodePoint { position: usize, code_point: char },
+    /// A label written in Unicode can't be converted into an A-label
+    InvalidIdna { position: usize },
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::EmptyLabel { position } => {
+                write!(f, "empty label at position {}", position)
+            }
+            NameError::LabelTooLong { position, length } => write!(
+                f,
+                "label of {} bytes at position {} is longer than 63 bytes",
+                length, position
+            ),
+            NameError::NameTooLong { length } => {
+                write!(
+                    f,
+                    "domain name of {} bytes is longer than 255 bytes",
+                    length
+                )
+            }
+            NameError::InvalidEscape { position } => {
+                write!(f, "invalid escape sequence at position {}", position)
+            }
+            NameError::DisallowedCodePoint {
+                position,
+                code_point,
+            } => write!(
+                f,
+                "code point U+{:04X} {:?} is not allowed in the label at position {}",
+                *code_point as u32, code_point, position
+            ),
+            NameError::InvalidIdna { position } => write!(
+                f,
+                "label at position {} is not a valid internationalized label",
+                position
+            ),
+        }
+    }
+}
+
+impl Error for NameError {}
+
+/// Error raised when a master file can't be parsed, with the position of
+/// the offending text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneError {
+    /// File the error was found in, if the text was read from a file
+    pub file: Option<PathBuf>,
+    /// Line of the error, starting at 1, `None` when the error is not in
+    /// the text, as for a file that can't be read
+    pub line: Option<usize>,
+    /// Column of the error in characters, starting at 1, `None` along with
+    /// the line
+    pub column: Option<usize>,
+    /// What went wrong
+    pub kind: ZoneErrorKind,
+}
+
+/// Kind of error raised while parsing a master file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZoneErrorKind {
+    /// A parenthesis is closed without being opened, or never closed
+    UnbalancedParenthesis,
+    /// A quoted string is not closed before the end of the line
+    UnterminatedString,
+    /// A `$` directive is not one of the supported ones
+    UnknownDirective(String),
+    /// A relative name or `@` is used before any origin is known
+    MissingOrigin,
+    /// A record with a blank owner comes before any owner
+    MissingOwner,
+    /// A record has no TTL and no default TTL is known
+    MissingTtl,
+    /// A record type is expected but the text is not one
+    UnknownType(String),
+    /// The entry ends before all the fields are read
+    MissingField,
+    /// Text is left after the last field of the entry
+    TrailingText(String),
+    /// A domain name is invalid
+    InvalidName(NameError),
+    /// A field is not a valid number, TTL or address
+    InvalidValue(String),
+    /// A character string is longer than 255 bytes or badly escaped
+    InvalidString,
+    /// The generic rdata form (RFC 3597) is malformed
+    InvalidRData(String),
+    /// Rdata of this type can only be written in the generic form
+    UnsupportedType(RecordType),
+    /// A `$GENERATE` directive is malformed
+    InvalidGenerate(String),
+    /// The master file can't be read
+    Unreadable(String),
+    /// A `$INCLUDE` file can't be read
+    Include { path: PathBuf, message: String },
+    /// `$INCLUDE` directives are nested too deeply
+    IncludeDepth,
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: ", line, column)?,
+            _ if self.file.is_some() => write!(f, " ")?,
+            _ => {}
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ZoneErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZoneErrorKind::UnbalancedParenthesis => write!(f, "unbalanced parenthesis"),
+            ZoneErrorKind::UnterminatedString => write!(f, "unterminated quoted string"),
+            ZoneErrorKind::UnknownDirective(directive) => {
+                write!(f, "unknown directive {}", directive)
+            }
+            ZoneErrorKind::MissingOrigin => write!(f, "relative name without an origin"),
+            ZoneErrorKind::MissingOwner => write!(f, "record without an owner"),
+            ZoneErrorKind::MissingTtl => write!(f, "record without a TTL"),
+            ZoneErrorKind::UnknownType(text) => write!(f, "unknown record type {}", text),
+            ZoneErrorKind::MissingField => write!(f, "missing field"),
+            ZoneErrorKind::TrailingText(text) => write!(f, "unexpected text {}", text),
+            ZoneErrorKind::InvalidName(err) => write!(f, "invalid name: {}", err),
+            ZoneErrorKind::InvalidValue(text) => write!(f, "invalid value {}", text),
+            ZoneErrorKind::InvalidString => write!(f, "invalid character string"),
+            ZoneErrorKind::InvalidRData(message) => write!(f, "invalid rdata: {}", message),
+            ZoneErrorKind::UnsupportedType(rr_type) => {
+                write!(f, "rdata of type {} must use the generic \\# form", rr_type)
+            }
+            ZoneErrorKind::InvalidGenerate(message) => {
+                write!(f, "invalid $GENERATE: {}", message)
+            }
+            ZoneErrorKind::Unreadable(message) => write!(f, "can't read file: {}", message),
+            ZoneErrorKind::Include { path, message } => {
+                write!(f, "can't include {}: {}", path.display(), message)
+            }
+            ZoneErrorKind::IncludeDepth => write!(f, "$INCLUDE nested too deeply"),
+        }
+    }
+}
+
+impl Error for ZoneError {}
+
+/// Error raised when a lookup does not end with an answer
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The name does not exist (NXDOMAIN)
+    NxDomain { name: Name },
+    /// The name exists but holds no record of the type asked for
+    NoData { name: Name, q_type: RecordType },
+    /// The server failed to process the query (SERVFAIL)
+    ServFail,
+    /// The server refused to answer (REFUSED)
+    Refused,
+    /// The server answered with another error response code
+    ServerError(Rcode),
+    /// No response came in time
+    Timeout,
+    /// A query could not be sent or a response could not be received
+    Network(io::Error),
+    /// A response could not be parsed
+    Malformed(ParseError),
+    /// Every attempt was used following referrals without getting an answer,
+    /// or a referral gave no address for its name servers
+    NoAnswer,
+    /// The query could not be written in wire format
+    InvalidQuery(EncodeError),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::NxDomain { name } => write!(f, "{} does not exist", name),
+            ResolveError::NoData { name, q_type } => {
+                write!(f, "{} has no {} record", name, q_type)
+            }
+            ResolveError::ServFail => write!(f, "server failure"),
+            ResolveError::Refused => write!(f, "query refused"),
+            ResolveError::ServerError(rcode) => write!(f, "server answered {}", rcode),
+            ResolveError::Timeout => write!(f, "timed out waiting for a response"),
+            ResolveError::Network(err) => write!(f, "network error: {}", err),
+            ResolveError::Malformed(err) => write!(f, "malformed response: {}", err),
+            ResolveError::NoAnswer => write!(f, "no answer after following referrals"),
+            ResolveError::InvalidQuery(err) => write!(f, "invalid query: {}", err),
+        }
+    }
+}
+
+impl Error for ResolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolveError::Network(err) => Some(err),
+            ResolveError::Malformed(err) => Some(err),
+            ResolveError::InvalidQuery(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ResolveError {
+    /// Sort timeouts out of other network errors
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ResolveError::Timeout,
+            _ => ResolveError::Network(err),
+        }
+    }
+}
+
+impl From<ParseError> for ResolveError {
+    fn from(err: ParseError) -> Self {
+        ResolveError::Malformed(err)
+    }
+}
+
+impl From<EncodeError> for ResolveError {
+    fn from(err: EncodeError) -> Self {
+        ResolveError::InvalidQuery(err)
+    }
+}
@@ -0,0 +1,499 @@
+use std::fmt;
+
+use crate::error::ParseError;
+use crate::utility;
+
+/// Kind of query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    /// Standard query
+    Query,
+    /// Inverse query, obsoleted by RFC 3425
+    IQuery,
+    /// Server status request
+    Status,
+    /// Zone change notification (RFC 1996)
+    Notify,
+    /// Dynamic update (RFC 2136)
+    Update,
+    /// DNS stateful operations (RFC 8490)
+    Dso,
+    /// Unassigned opcode
+    Unknown(u8),
+}
+
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Query,
+            1 => Opcode::IQuery,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            6 => Opcode::Dso,
+            _ => Opcode::Unknown(value),
+        }
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Query => 0,
+            Opcode::IQuery => 1,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Dso => 6,
+            Opcode::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Query => write!(f, "QUERY"),
+            Opcode::IQuery => write!(f, "IQUERY"),
+            Opcode::Status => write!(f, "STATUS"),
+            Opcode::Notify => write!(f, "NOTIFY"),
+            Opcode::Update => write!(f, "UPDATE"),
+            Opcode::Dso => write!(f, "DSO"),
+            Opcode::Unknown(value) => write!(f, "OPCODE{}", value),
+        }
+    }
+}
+
+/// Response code. Values above 15 only fit in a message together with the
+/// extended bits carried by EDNS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rcode {
+    /// No error condition
+    NoError,
+    /// Format error - the name server was unable to interpret the query
+    FormErr,
+    /// Server failure
+    ServFail,
+    /// Name error - the domain name referenced in the query does not exist
+    NXDomain,
+    /// Not implemented
+    NotImp,
+    /// Refused for policy reasons
+    Refused,
+    /// Name exists when it should not (RFC 2136)
+    YXDomain,
+    /// RR set exists when it should not (RFC 2136)
+    YXRRSet,
+    /// RR set that should exist does not (RFC 2136)
+    NXRRSet,
+    /// Server not authoritative for zone, or not authorized (RFC 2136, 8945)
+    NotAuth,
+    /// Name not contained in zone (RFC 2136)
+    NotZone,
+    /// DSO-TYPE not implemented (RFC 8490)
+    DsoTypeNI,
+    /// Bad OPT version (RFC 6891)
+    BadVers,
+    /// Bad or missing server cookie (RFC 7873)
+    BadCookie,
+    /// Unassigned or TSIG-specific response code
+    Unknown(u16),
+}
+
+impl From<u16> for Rcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Rcode::NoError,
+            1 => Rcode::FormErr,
+            2 => Rcode::ServFail,
+            3 => Rcode::NXDomain,
+            4 => Rcode::NotImp,
+            5 => Rcode::Refused,
+            6 => Rcode::YXDomain,
+            7 => Rcode::YXRRSet,
+            8 => Rcode::NXRRSet,
+            9 => Rcode::NotAuth,
+            10 => Rcode::NotZone,
+            11 => Rcode::DsoTypeNI,
+            16 => Rcode::BadVers,
+            23 => Rcode::BadCookie,
+            _ => Rcode::Unknown(value),
+        }
+    }
+}
+
+impl From<Rcode> for u16 {
+    fn from(value: Rcode) -> Self {
+        match value {
+            Rcode::NoError => 0,
+            Rcode::FormErr => 1,
+            Rcode::ServFail => 2,
+            Rcode::NXDomain => 3,
+            Rcode::NotImp => 4,
+            Rcode::Refused => 5,
+            Rcode::YXDomain => 6,
+            Rcode::YXRRSet => 7,
+            Rcode::NXRRSet => 8,
+            Rcode::NotAuth => 9,
+            Rcode::NotZone => 10,
+            Rcode::DsoTypeNI => 11,
+            Rcode::BadVers => 16,
+            Rcode::BadCookie => 23,
+            Rcode::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Rcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rcode::NoError => write!(f, "NOERROR"),
+            Rcode::FormErr => write!(f, "FORMERR"),
+            Rcode::ServFail => write!(f, "SERVFAIL"),
+            Rcode::NXDomain => write!(f, "NXDOMAIN"),
+            Rcode::NotImp => write!(f, "NOTIMP"),
+            Rcode::Refused => write!(f, "REFUSED"),
+            Rcode::YXDomain => write!(f, "YXDOMAIN"),
+            Rcode::YXRRSet => write!(f, "YXRRSET"),
+            Rcode::NXRRSet => write!(f, "NXRRSET"),
+            Rcode::NotAuth => write!(f, "NOTAUTH"),
+            Rcode::NotZone => write!(f, "NOTZONE"),
+            Rcode::DsoTypeNI => write!(f, "DSOTYPENI"),
+            Rcode::BadVers => write!(f, "BADVERS"),
+            Rcode::BadCookie => write!(f, "BADCOOKIE"),
+            Rcode::Unknown(value) => write!(f, "RCODE{}", value),
+        }
+    }
+}
+
+/// Flag section in DNS header
+///
+/// ```text
+///   15  14  13  12  11  10   9   8   7   6   5   4   3   2   1   0
+/// +---+---------------+---+---+---+---+---+---+---+---------------+
+/// |QR |    Opcode     |AA |TC |RD |RA | Z |AD |CD |     RCODE     |
+/// +---+---------------+---+---+---+---+---+---+---+---------------+
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flag {
+    bits: u16,
+}
+
+impl Flag {
+    const QR: u32 = 15;
+    const AA: u32 = 10;
+    const TC: u32 = 9;
+    const RD: u32 = 8;
+    const RA: u32 = 7;
+    const Z: u32 = 6;
+    const AD: u32 = 5;
+    const CD: u32 = 4;
+
+    /// Transform the flag to a two-octet number
+    pub fn to_be_bytes(&self) -> u16 {
+        self.bits
+    }
+
+    /// Parse a two-octet number to DNS flag
+    pub fn parse(flag: u16) -> Flag {
+        Flag { bits: flag }
+    }
+
+    fn bit(&self, bit: u32) -> bool {
+        utility::get_bits_range(self.bits, bit, bit + 1) == 1
+    }
+
+    fn set_bit(&mut self, bit: u32, value: bool) {
+        self.bits = self.bits & !(1 << bit) | (value as u16) << bit;
+    }
+
+    /// Whether it is a query (false) or a response (true)
+    pub fn qr(&self) -> bool {
+        self.bit(Flag::QR)
+    }
+
+    /// Mark it as a query (false) or a response (true)
+    pub fn set_qr(&mut self, value: bool) {
+        self.set_bit(Flag::QR, value);
+    }
+
+    /// Kind of query
+    pub fn opcode(&self) -> Opcode {
+        Opcode::from(utility::get_bits_range(self.bits, 11, 15) as u8)
+    }
+
+    /// Set the kind of query
+    pub fn set_opcode(&mut self, opcode: Opcode) {
+        let value = u8::from(opcode) as u16 & 0x0F;
+        self.bits = self.bits & !(0x0F << 11) | value << 11;
+    }
+
+    /// Authoritative answer - valid in response
+    pub fn aa(&self) -> bool {
+        self.bit(Flag::AA)
+    }
+
+    /// Set whether the answer is authoritative
+    pub fn set_aa(&mut self, value: bool) {
+        self.set_bit(Flag::AA, value);
+    }
+
+    /// Truncation - message was truncated due to excessive length
+    pub fn tc(&self) -> bool {
+        self.bit(Flag::TC)
+    }
+
+    /// Set whether the message was truncated
+    pub fn set_tc(&mut self, value: bool) {
+        self.set_bit(Flag::TC, value);
+    }
+
+    /// Recursion desired - direct name server to pursue query recursively
+    pub fn rd(&self) -> bool {
+        self.bit(Flag::RD)
+    }
+
+    /// Set whether recursion is desired
+    pub fn set_rd(&mut self, value: bool) {
+        self.set_bit(Flag::RD, value);
+    }
+
+    /// Recursion available - denotes whether recursive query support is
+    /// available in the name server
+    pub fn ra(&self) -> bool {
+        self.bit(Flag::RA)
+    }
+
+    /// Set whether recursion is available
+    pub fn set_ra(&mut self, value: bool) {
+        self.set_bit(Flag::RA, value);
+    }
+
+    /// Reserved for future use, must be zero
+    pub fn z(&self) -> bool {
+        self.bit(Flag::Z)
+    }
+
+    /// Set the reserved bit, which must be zero
+    pub fn set_z(&mut self, value: bool) {
+        self.set_bit(Flag::Z, value);
+    }
+
+    /// Authentic data - all data in the response was validated (RFC 4035)
+    pub fn ad(&self) -> bool {
+        self.bit(Flag::AD)
+    }
+
+    /// Set whether all data in the response was validated
+    pub fn set_ad(&mut self, value: bool) {
+        self.set_bit(Flag::AD, value);
+    }
+
+    /// Checking disabled - the resolver should not validate (RFC 4035)
+    pub fn cd(&self) -> bool {
+        self.bit(Flag::CD)
+    }
+
+    /// Set whether the resolver should not validate
+    pub fn set_cd(&mut self, value: bool) {
+        self.set_bit(Flag::CD, value);
+    }
+
+    /// Lower four bits of the response code
+    pub fn rcode(&self) -> Rcode {
+        Rcode::from(utility::get_bits_range(self.bits, 0, 4))
+    }
+
+    /// Set the lower four bits of the response code
+    pub fn set_rcode(&mut self, rcode: Rcode) {
+        self.bits = self.bits & !0x0F | u16::from(rcode) & 0x0F;
+    }
+}
+
+impl fmt::Display for Flag {
+    /// Write the names of the flag bits that are set, e.g. `qr rd ra`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits = [
+            ("qr", self.qr()),
+            ("aa", self.aa()),
+            ("tc", self.tc()),
+            ("rd", self.rd()),
+            ("ra", self.ra()),
+            ("ad", self.ad()),
+            ("cd", self.cd()),
+        ];
+        let names = bits
+            .iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+/// DNS Header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Identifier from the DNS client
+    pub id: u16,
+    /// DNS Flag
+    pub flags: Flag,
+    /// Number of questions
+    pub qd_cnt: u16,
+    /// Number of answers
+    pub an_cnt: u16,
+    /// Number of authority records
+    pub ns_cnt: u16,
+    /// Number of additional records
+    pub ar_cnt: u16,
+}
+
+impl Header {
+    /// Transform to a vector of bytes
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        let mut header = vec![];
+
+        let mut bytes = self.id.to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        bytes = self.flags.to_be_bytes().to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        bytes = self.qd_cnt.to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        bytes = self.an_cnt.to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        bytes = self.ns_cnt.to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        bytes = self.ar_cnt.to_be_bytes().to_vec();
+        header.append(&mut bytes);
+
+        header
+    }
+
+    /// Parse a vector of bytes to DNS header
+    pub fn parse(message: &[u8], start: usize) -> Result<(usize, Header), ParseError> {
+        if message.len() < start + 12 {
+            return Err(ParseError::TruncatedHeader { offset: start });
+        }
+        let id = utility::read_u16(message, start)?;
+        let flags = Flag::parse(utility::read_u16(message, start + 2)?);
+        let qd_cnt = utility::read_u16(message, start + 4)?;
+        let an_cnt = utility::read_u16(message, start + 6)?;
+        let ns_cnt = utility::read_u16(message, start + 8)?;
+        let ar_cnt = utility::read_u16(message, start + 10)?;
+
+        let h = Header {
+            id,
+            flags,
+            qd_cnt,
+            an_cnt,
+            ns_cnt,
+            ar_cnt,
+        };
+
+        Ok((start + 12, h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+
+    #[test]
+    fn create_header() {
+        let mut dns_flags = Flag::default();
+        dns_flags.set_rd(true);
+
+        let dns_header = Header {
+            id: 1,
+            flags: dns_flags,
+            qd_cnt: 1,
+            an_cnt: 0,
+            ns_cnt: 0,
+            ar_cnt: 0,
+        };
+
+        assert_eq!(
+            dns_header.to_be_bytes(),
+            vec![0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn parse_truncated_header() {
+        let bytes = vec![
+            0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            Header::parse(&bytes, 0).err(),
+            Some(ParseError::TruncatedHeader { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn parse_response_flags() {
+        let flag = Flag::parse(0x8583);
+        assert!(flag.qr());
+        assert_eq!(flag.opcode(), Opcode::Query);
+        assert!(flag.aa());
+        assert!(!flag.tc());
+        assert!(flag.rd());
+        assert!(flag.ra());
+        assert!(!flag.ad());
+        assert_eq!(flag.rcode(), Rcode::NXDomain);
+        assert_eq!(flag.rcode().to_string(), "NXDOMAIN");
+    }
+
+    #[test]
+    fn round_trip_every_flag_word() {
+        for word in 0..=u16::MAX {
+            let flag = Flag::parse(word);
+            assert_eq!(flag.to_be_bytes(), word);
+
+            let mut rebuilt = Flag::default();
+            rebuilt.set_qr(flag.qr());
+            rebuilt.set_opcode(flag.opcode());
+            rebuilt.set_aa(flag.aa());
+            rebuilt.set_tc(flag.tc());
+            rebuilt.set_rd(flag.rd());
+            rebuilt.set_ra(flag.ra());
+            rebuilt.set_z(flag.z());
+            rebuilt.set_ad(flag.ad());
+            rebuilt.set_cd(flag.cd());
+            rebuilt.set_rcode(flag.rcode());
+            assert_eq!(rebuilt, flag);
+        }
+    }
+
+    #[test]
+    fn round_trip_codes() {
+        for value in 0..16u8 {
+            assert_eq!(u8::from(Opcode::from(value)), value);
+        }
+        for value in 0..4096u16 {
+            assert_eq!(u16::from(Rcode::from(value)), value);
+        }
+        assert_eq!(Opcode::Notify.to_string(), "NOTIFY");
+        assert_eq!(Opcode::from(3).to_string(), "OPCODE3");
+        assert_eq!(Rcode::from(16).to_string(), "BADVERS");
+    }
+
+    proptest! {
+        #[test]
+        fn flag_round_trip(flag in strategies::flag()) {
+            prop_assert_eq!(Flag::parse(flag.to_be_bytes()), flag);
+        }
+
+        #[test]
+        fn header_round_trip(header in strategies::header()) {
+            let bytes = header.to_be_bytes();
+            prop_assert_eq!(Header::parse(&bytes, 0), Ok((12, header)));
+        }
+    }
+}
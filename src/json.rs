@@ -0,0 +1,486 @@
+//! JSON representation of DNS messages (RFC 8427), through serde
+//!
+//! Messages are written with both their structured members and their wire
+//! form in `messageOctetsHEX`, and records with their rdata both in
+//! presentation format (`rdataMX`, ...) and in `RDATAHEX`. When reading, the
+//! wire forms take precedence since they are exact, and the structured
+//! members stand in for them when missing
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::edns::Edns;
+use crate::header::{Flag, Header, Opcode, Rcode};
+use crate::message::{section_count, DnsMessage};
+use crate::name::Name;
+use crate::question::Question;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::RecordType;
+use crate::utility;
+use crate::zone;
+
+/// Header members of a message object
+#[derive(Serialize, Deserialize)]
+struct HeaderRepr {
+    #[serde(rename = "ID")]
+    id: Option<u16>,
+    #[serde(rename = "QR")]
+    qr: Option<bool>,
+    #[serde(rename = "Opcode")]
+    opcode: Option<u8>,
+    #[serde(rename = "AA")]
+    aa: Option<bool>,
+    #[serde(rename = "TC")]
+    tc: Option<bool>,
+    #[serde(rename = "RD")]
+    rd: Option<bool>,
+    #[serde(rename = "RA")]
+    ra: Option<bool>,
+    #[serde(rename = "AD")]
+    ad: Option<bool>,
+    #[serde(rename = "CD")]
+    cd: Option<bool>,
+    #[serde(rename = "RCODE")]
+    rcode: Option<u8>,
+    #[serde(rename = "QDCOUNT")]
+    qd_cnt: Option<u16>,
+    #[serde(rename = "ANCOUNT")]
+    an_cnt: Option<u16>,
+    #[serde(rename = "NSCOUNT")]
+    ns_cnt: Option<u16>,
+    #[serde(rename = "ARCOUNT")]
+    ar_cnt: Option<u16>,
+}
+
+impl From<&Header> for HeaderRepr {
+    fn from(header: &Header) -> Self {
+        let flags = header.flags;
+        HeaderRepr {
+            id: Some(header.id),
+            qr: Some(flags.qr()),
+            opcode: Some(flags.opcode().into()),
+            aa: Some(flags.aa()),
+            tc: Some(flags.tc()),
+            rd: Some(flags.rd()),
+            ra: Some(flags.ra()),
+            ad: Some(flags.ad()),
+            cd: Some(flags.cd()),
+            rcode: Some(u16::from(flags.rcode()) as u8),
+            qd_cnt: Some(header.qd_cnt),
+            an_cnt: Some(header.an_cnt),
+            ns_cnt: Some(header.ns_cnt),
+            ar_cnt: Some(header.ar_cnt),
+        }
+    }
+}
+
+impl HeaderRepr {
+    /// Header with these members, missing flags being clear and missing
+    /// counts being taken from `counts`
+    fn into_header(self, counts: [u16; 4]) -> Header {
+        let mut flags = Flag::default();
+        flags.set_qr(self.qr.unwrap_or_default());
+        flags.set_opcode(Opcode::from(self.opcode.unwrap_or_default()));
+        flags.set_aa(self.aa.unwrap_or_default());
+        flags.set_tc(self.tc.unwrap_or_default());
+        flags.set_rd(self.rd.unwrap_or_default());
+        flags.set_ra(self.ra.unwrap_or_default());
+        flags.set_ad(self.ad.unwrap_or_default());
+        flags.set_cd(self.cd.unwrap_or_default());
+        flags.set_rcode(Rcode::from(self.rcode.unwrap_or_default() as u16));
+
+        Header {
+            id: self.id.unwrap_or_default(),
+            flags,
+            qd_cnt: self.qd_cnt.unwrap_or(counts[0]),
+            an_cnt: self.an_cnt.unwrap_or(counts[1]),
+            ns_cnt: self.ns_cnt.unwrap_or(counts[2]),
+            ar_cnt: self.ar_cnt.unwrap_or(counts[3]),
+        }
+    }
+}
+
+impl Serialize for Header {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        HeaderRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Header {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(HeaderRepr::deserialize(deserializer)?.into_header([0; 4]))
+    }
+}
+
+/// Message object
+#[derive(Serialize, Deserialize)]
+struct MessageRepr<'a> {
+    #[serde(flatten)]
+    header: HeaderRepr,
+    #[serde(rename = "questionRRs", default)]
+    questions: Cow<'a, [Question]>,
+    #[serde(rename = "answerRRs", default)]
+    answers: Cow<'a, [ResourceRecord]>,
+    #[serde(rename = "authorityRRs", default)]
+    authorities: Cow<'a, [ResourceRecord]>,
+    #[serde(rename = "additionalRRs", default)]
+    additionals: Cow<'a, [ResourceRecord]>,
+    #[serde(rename = "messageOctetsHEX")]
+    message_hex: Option<String>,
+}
+
+impl Serialize for DnsMessage {
+    /// Write the message object, the OPT record being part of the additional
+    /// records as on the wire
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let additionals = match &self.edns {
+            Some(edns) => {
+                let mut additionals = self.additionals.clone();
+                additionals.push(edns.to_record());
+                Cow::Owned(additionals)
+            }
+            None => Cow::Borrowed(self.additionals.as_slice()),
+        };
+
+        MessageRepr {
+            header: HeaderRepr::from(&self.counted_header().map_err(S::Error::custom)?),
+            questions: Cow::Borrowed(&self.questions),
+            answers: Cow::Borrowed(&self.answers),
+            authorities: Cow::Borrowed(&self.authorities),
+            additionals,
+            message_hex: Some(utility::hex(&self.to_be_bytes().map_err(S::Error::custom)?)),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DnsMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MessageRepr::deserialize(deserializer)?;
+        if let Some(hex) = &repr.message_hex {
+            let bytes = utility::decode_hex(hex)
+                .ok_or_else(|| D::Error::custom("invalid messageOctetsHEX"))?;
+            return DnsMessage::parse(&bytes).map_err(D::Error::custom);
+        }
+
+        let mut additionals = vec![];
+        let mut edns = None;
+        for rr in repr.additionals.into_owned() {
+            if rr.an_type != RecordType::OPT {
+                additionals.push(rr);
+            } else if edns.is_none() {
+                edns = Some(Edns::parse(&rr, 0).map_err(D::Error::custom)?);
+            } else {
+                return Err(D::Error::custom("more than one OPT record"));
+            }
+        }
+        let count = |count| section_count(count).map_err(D::Error::custom);
+        let counts = [
+            count(repr.questions.len())?,
+            count(repr.answers.len())?,
+            count(repr.authorities.len())?,
+            count(additionals.len() + edns.is_some() as usize)?,
+        ];
+
+        Ok(DnsMessage {
+            header: repr.header.into_header(counts),
+            questions: repr.questions.into_owned(),
+            answers: repr.answers.into_owned(),
+            authorities: repr.authorities.into_owned(),
+            additionals,
+            edns,
+        })
+    }
+}
+
+/// Question object
+#[derive(Serialize, Deserialize)]
+struct QuestionRepr {
+    #[serde(rename = "NAME")]
+    name: String,
+    #[serde(rename = "TYPE")]
+    q_type: Option<u16>,
+    #[serde(rename = "TYPEname")]
+    type_name: Option<String>,
+    #[serde(rename = "CLASS")]
+    q_class: Option<u16>,
+    #[serde(rename = "CLASSname")]
+    class_name: Option<String>,
+}
+
+impl Serialize for Question {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        QuestionRepr {
+            name: self.q_name.to_string(),
+            q_type: Some(self.q_type.into()),
+            type_name: Some(self.q_type.to_string()),
+            q_class: Some(self.q_class.into()),
+            class_name: Some(self.q_class.to_string()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Question {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = QuestionRepr::deserialize(deserializer)?;
+        Ok(Question {
+            q_name: parse_name(&repr.name)?,
+            q_type: code_or_name(repr.q_type, repr.type_name, "TYPE")?,
+            q_class: code_or_name(repr.q_class, repr.class_name, "CLASS")?,
+        })
+    }
+}
+
+/// Resource record object
+#[derive(Serialize, Deserialize)]
+struct RecordRepr {
+    #[serde(rename = "NAME")]
+    name: String,
+    #[serde(rename = "TYPE")]
+    an_type: Option<u16>,
+    #[serde(rename = "TYPEname")]
+    type_name: Option<String>,
+    #[serde(rename = "CLASS")]
+    an_class: Option<u16>,
+    #[serde(rename = "CLASSname")]
+    class_name: Option<String>,
+    #[serde(rename = "TTL")]
+    ttl: u32,
+    #[serde(rename = "RDLENGTH")]
+    rdlength: Option<u16>,
+    #[serde(rename = "RDATAHEX")]
+    rdata_hex: Option<String>,
+    /// Rdata in presentation format, named `rdata` followed by the type
+    #[serde(flatten)]
+    rdata: BTreeMap<String, String>,
+}
+
+impl Serialize for ResourceRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.an_rdata.to_be_bytes().map_err(S::Error::custom)?;
+        let mut rdata = BTreeMap::new();
+        if !matches!(self.an_rdata, RData::Unknown(_)) {
+            rdata.insert(format!("rdata{}", self.an_type), self.an_rdata.to_string());
+        }
+
+        RecordRepr {
+            name: self.an_name.to_string(),
+            an_type: Some(self.an_type.into()),
+            type_name: Some(self.an_type.to_string()),
+            an_class: Some(self.an_class.into()),
+            class_name: Some(self.an_class.to_string()),
+            ttl: self.an_ttl,
+            rdlength: Some(data.len() as u16),
+            rdata_hex: Some(utility::hex(&data)),
+            rdata,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = RecordRepr::deserialize(deserializer)?;
+        let an_type: RecordType = code_or_name(repr.an_type, repr.type_name, "TYPE")?;
+        let an_rdata = match (
+            &repr.rdata_hex,
+            repr.rdata.get(&format!("rdata{}", an_type)),
+        ) {
+            (Some(hex), _) => {
+                let data =
+                    utility::decode_hex(hex).ok_or_else(|| D::Error::custom("invalid RDATAHEX"))?;
+                RData::parse(&data, 0, data.len(), an_type).map_err(D::Error::custom)?
+            }
+            (None, Some(text)) => zone::parse_rdata(text, an_type).map_err(D::Error::custom)?,
+            (None, None) => {
+                return Err(D::Error::custom(format!(
+                    "missing RDATAHEX or rdata{}",
+                    an_type
+                )))
+            }
+        };
+
+        Ok(ResourceRecord {
+            an_name: parse_name(&repr.name)?,
+            an_type,
+            an_class: code_or_name(repr.an_class, repr.class_name, "CLASS")?,
+            an_ttl: repr.ttl,
+            an_rdata,
+        })
+    }
+}
+
+fn parse_name<E: serde::de::Error>(text: &str) -> Result<Name, E> {
+    Name::from_str(text).map_err(E::custom)
+}
+
+/// Type or class given by its code, or else by its name
+fn code_or_name<T, E>(code: Option<u16>, name: Option<String>, member: &str) -> Result<T, E>
+where
+    T: From<u16> + FromStr<Err = String>,
+    E: serde::de::Error,
+{
+    match (code, name) {
+        (Some(code), _) => Ok(T::from(code)),
+        (None, Some(name)) => T::from_str(&name).map_err(E::custom),
+        (None, None) => Err(E::custom(format!("missing {} or {}name", member, member))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use crate::types::Class;
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+    use std::net::Ipv4Addr;
+
+    fn response() -> DnsMessage {
+        let mut message = DnsMessage::query("example.com".parse().unwrap())
+            .id(0x1234)
+            .recursion_desired(true)
+            .build();
+        message.header.flags.set_qr(true);
+        message.answers.push(ResourceRecord {
+            an_name: "example.com".parse().unwrap(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+        message.header.an_cnt = 1;
+        message
+    }
+
+    /// Remove the wire forms standing in for structured members
+    fn strip_wire_forms(value: &mut Value) {
+        match value {
+            Value::Object(members) => {
+                members.remove("messageOctetsHEX");
+                if members.keys().any(|key| key.starts_with("rdata")) {
+                    members.remove("RDATAHEX");
+                }
+                members.values_mut().for_each(strip_wire_forms);
+            }
+            Value::Array(values) => values.iter_mut().for_each(strip_wire_forms),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn serialize_rfc8427_members() {
+        let message = response();
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["ID"], 0x1234);
+        assert_eq!(value["QR"], true);
+        assert_eq!(value["RD"], true);
+        assert_eq!(value["Opcode"], 0);
+        assert_eq!(value["ANCOUNT"], 1);
+        assert_eq!(
+            value["questionRRs"],
+            json!([{
+                "NAME": "example.com.",
+                "TYPE": 1,
+                "TYPEname": "A",
+                "CLASS": 1,
+                "CLASSname": "IN"
+            }])
+        );
+        assert_eq!(
+            value["answerRRs"][0],
+            json!({
+                "NAME": "example.com.",
+                "TYPE": 1,
+                "TYPEname": "A",
+                "CLASS": 1,
+                "CLASSname": "IN",
+                "TTL": 300,
+                "RDLENGTH": 4,
+                "RDATAHEX": "c0000201",
+                "rdataA": "192.0.2.1"
+            })
+        );
+        assert_eq!(
+            value["messageOctetsHEX"],
+            utility::hex(&message.to_be_bytes().unwrap())
+        );
+    }
+
+    #[test]
+    fn deserialize_structured_members() {
+        let value = json!({
+            "ID": 0x1234,
+            "QR": true,
+            "RD": true,
+            "questionRRs": [{ "NAME": "example.com", "TYPEname": "A", "CLASS": 1 }],
+            "answerRRs": [{
+                "NAME": "example.com",
+                "TYPE": 1,
+                "CLASSname": "IN",
+                "TTL": 300,
+                "rdataA": "192.0.2.1"
+            }],
+        });
+        let message: DnsMessage = serde_json::from_value(value).unwrap();
+        assert_eq!(message, response());
+    }
+
+    #[test]
+    fn reject_oversized_section() {
+        let mut message = response();
+        let question = message.questions[0].clone();
+        message.questions = vec![question; 65536];
+        let err = serde_json::to_value(&message).unwrap_err();
+        assert!(err.to_string().contains("65536 entries"));
+
+        let questions = vec![json!({ "NAME": "example.com", "TYPE": 1, "CLASS": 1 }); 65536];
+        let value = json!({ "ID": 1, "questionRRs": questions });
+        let err = serde_json::from_value::<DnsMessage>(value).unwrap_err();
+        assert!(err.to_string().contains("65536 entries"));
+    }
+
+    #[test]
+    fn deserialize_wire_form() {
+        let message = response();
+        let value = json!({ "messageOctetsHEX": utility::hex(&message.to_be_bytes().unwrap()) });
+        assert_eq!(
+            serde_json::from_value::<DnsMessage>(value).unwrap(),
+            message
+        );
+
+        let value = json!({ "messageOctetsHEX": "c0" });
+        assert!(serde_json::from_value::<DnsMessage>(value).is_err());
+    }
+
+    #[test]
+    fn reject_record_without_rdata() {
+        let value = json!({ "NAME": "example.com", "TYPE": 1, "CLASS": 1, "TTL": 300 });
+        let err = serde_json::from_value::<ResourceRecord>(value).unwrap_err();
+        assert_eq!(err.to_string(), "missing RDATAHEX or rdataA");
+    }
+
+    proptest! {
+        #[test]
+        fn json_round_trip(message in strategies::message()) {
+            let json = serde_json::to_string(&message).unwrap();
+            prop_assert_eq!(serde_json::from_str::<DnsMessage>(&json).unwrap(), message);
+        }
+
+        #[test]
+        fn structured_round_trip(mut message in strategies::message()) {
+            // Z has no member of its own
+            message.header.flags.set_z(false);
+            let mut value = serde_json::to_value(&message).unwrap();
+            strip_wire_forms(&mut value);
+            prop_assert_eq!(serde_json::from_value::<DnsMessage>(value).unwrap(), message);
+        }
+    }
+}
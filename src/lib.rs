@@ -0,0 +1,41 @@
+//! DNS message codec, with an optional resolver sending queries over the
+//! network
+//!
+//! The `codec` feature provides the wire and text formats of messages, the
+//! `client` feature adds [`client::DnsClient`] and the `cli` feature builds
+//! the command line tool on top of it. The `tls`, `https` and `quic` features
+//! add encrypted transports for the client.
+
+#[cfg(feature = "codec")]
+pub mod edns;
+#[cfg(feature = "codec")]
+pub mod encoder;
+#[cfg(feature = "codec")]
+pub mod error;
+#[cfg(feature = "codec")]
+pub mod header;
+#[cfg(feature = "serde")]
+pub mod json;
+#[cfg(feature = "codec")]
+pub mod message;
+#[cfg(feature = "codec")]
+pub mod message_ref;
+#[cfg(feature = "codec")]
+pub mod name;
+#[cfg(feature = "codec")]
+pub mod question;
+#[cfg(feature = "codec")]
+pub mod rdata;
+#[cfg(feature = "codec")]
+pub mod rr;
+#[cfg(all(test, feature = "codec"))]
+mod strategies;
+#[cfg(feature = "codec")]
+pub mod types;
+#[cfg(feature = "codec")]
+pub mod utility;
+#[cfg(feature = "codec")]
+pub mod zone;
+
+#[cfg(feature = "client")]
+pub mod client;
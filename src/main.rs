@@ -1,17 +1,216 @@
-use clap::Parser;
-
-pub mod client;
+use base64::Engine;
+use clap::{ArgGroup, Parser};
+use dns_resolver::client::config::ResolverConfig;
+use dns_resolver::client::https::{HttpsClient, HttpsConfig, HttpsMethod};
+use dns_resolver::client::quic::QuicClient;
+use dns_resolver::client::quic::QuicConfig;
+use dns_resolver::client::tcp::TcpTransport;
+use dns_resolver::client::tls::{TlsClient, TlsConfig};
+use dns_resolver::client::transport::{Server, Transport};
+use dns_resolver::client::udp::UdpTransport;
+use dns_resolver::client::DnsClient;
+use dns_resolver::edns::{self, Edns, EdnsOption};
+use dns_resolver::name::Name;
+use dns_resolver::types::{Class, RecordType};
+use std::net::IpAddr;
+use std::process;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("encrypted").args(["tls", "https", "quic"])))]
 struct Options {
-    /// Host name that is needed to resolve
-    host: String,
-    /// DNS server
-    dns_server: String,
+    /// Host name that is needed to resolve, Unicode names are converted to
+    /// their ASCII form
+    host: Name,
+    /// DNS server, e.g. 192.0.2.1, [2001:db8::1]:5353 or dns.example.com
+    dns_server: Server,
+    /// Type of the records to ask for, e.g. A, AAAA, MX or TYPE65
+    #[arg(short = 't', long = "type", default_value = "A")]
+    q_type: RecordType,
+    /// Class of the records to ask for, e.g. IN or CH
+    #[arg(short = 'c', long = "class", default_value = "IN")]
+    q_class: Class,
+    /// Send plain DNS queries without an EDNS OPT record
+    #[arg(
+        long,
+        conflicts_with_all = ["bufsize", "dnssec", "nsid", "subnet", "cookie", "padding"]
+    )]
+    no_edns: bool,
+    /// UDP payload size advertised with EDNS
+    #[arg(long, default_value_t = edns::DEFAULT_UDP_PAYLOAD_SIZE)]
+    bufsize: u16,
+    /// Ask for DNSSEC records by setting the DO bit
+    #[arg(long)]
+    dnssec: bool,
+    /// Ask the server for its name server identifier
+    #[arg(long)]
+    nsid: bool,
+    /// Send an EDNS client subnet, e.g. 192.0.2.0/24
+    #[arg(long, value_parser = parse_subnet)]
+    subnet: Option<EdnsOption>,
+    /// Send a random client cookie
+    #[arg(long)]
+    cookie: bool,
+    /// Pad queries to a multiple of 128 bytes
+    #[arg(long)]
+    padding: bool,
+    /// Ask the server to resolve the query recursively (RD bit), which is
+    /// always done over TLS, HTTPS and QUIC as only recursive resolvers
+    /// offer these transports
+    #[arg(long)]
+    rd: bool,
+    /// Show internationalized names in answers in Unicode
+    #[arg(long)]
+    unicode: bool,
+    /// Send queries over TCP only
+    #[arg(long, conflicts_with = "encrypted")]
+    tcp: bool,
+    /// Send queries over TLS to port 853 (DNS over TLS)
+    #[arg(long)]
+    tls: bool,
+    /// Send queries over HTTPS to port 443 (DNS over HTTPS)
+    #[arg(long)]
+    https: bool,
+    /// Path of the DNS over HTTPS endpoint
+    #[arg(long, requires = "https", default_value = "/dns-query")]
+    https_path: String,
+    /// Send DNS over HTTPS queries with GET rather than POST
+    #[arg(long, requires = "https")]
+    https_get: bool,
+    /// Send queries over QUIC to port 853 (DNS over QUIC)
+    #[arg(long)]
+    quic: bool,
+    /// Send DNS over QUIC queries in 0-RTT data when resuming a session,
+    /// where they can be replayed
+    #[arg(long, requires = "quic")]
+    zero_rtt: bool,
+    /// Name the certificate of the server must be valid for, the server
+    /// address by default
+    #[arg(long, requires = "encrypted")]
+    tls_name: Option<String>,
+    /// Accept the server whose key has this base64 SHA-256 SPKI digest
+    /// instead of checking its certificate, may be repeated
+    #[arg(long, requires = "encrypted", value_parser = parse_pin)]
+    tls_pin: Vec<[u8; 32]>,
+    /// Seconds to wait for each response
+    #[arg(long, default_value_t = 2)]
+    timeout: u64,
+    /// Number of queries to send at most, referrals included
+    #[arg(long, default_value_t = 10)]
+    attempts: u32,
+}
+
+/// Parse a client subnet in `address/prefix` form
+fn parse_subnet(subnet: &str) -> Result<EdnsOption, String> {
+    let (address, prefix) = subnet.split_once('/').unwrap_or((subnet, ""));
+    let address = address
+        .parse::<IpAddr>()
+        .map_err(|err| format!("{}: {}", address, err))?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        "" => max_prefix,
+        prefix => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|&prefix| prefix <= max_prefix)
+            .ok_or_else(|| format!("Invalid prefix length: {}", prefix))?,
+    };
+    Ok(EdnsOption::client_subnet(address, prefix))
+}
+
+/// Parse a base64 SHA-256 digest of a SubjectPublicKeyInfo (RFC 7469)
+fn parse_pin(pin: &str) -> Result<[u8; 32], String> {
+    let digest = base64::engine::general_purpose::STANDARD
+        .decode(pin)
+        .map_err(|err| format!("{}: {}", pin, err))?;
+    digest
+        .try_into()
+        .map_err(|_| format!("{}: not a SHA-256 digest", pin))
+}
+
+/// Transport chosen by the options, UDP falling back to TCP by default
+fn transport(options: &Options) -> Result<Box<dyn Transport>, String> {
+    let tls = TlsConfig {
+        server_name: options.tls_name.clone(),
+        spki_pins: options.tls_pin.clone(),
+        ..TlsConfig::default()
+    };
+    let transport: Box<dyn Transport> = if options.quic {
+        let quic = QuicConfig {
+            tls,
+            zero_rtt: options.zero_rtt,
+            ..QuicConfig::default()
+        };
+        Box::new(QuicClient::new(quic).map_err(|err| format!("QUIC configuration: {}", err))?)
+    } else if options.https {
+        let https = HttpsConfig {
+            tls,
+            path: options.https_path.clone(),
+            method: if options.https_get {
+                HttpsMethod::Get
+            } else {
+                HttpsMethod::Post
+            },
+            ..HttpsConfig::default()
+        };
+        Box::new(HttpsClient::new(https).map_err(|err| format!("HTTPS configuration: {}", err))?)
+    } else if options.tls {
+        Box::new(TlsClient::new(tls).map_err(|err| format!("TLS configuration: {}", err))?)
+    } else if options.tcp {
+        Box::new(TcpTransport)
+    } else {
+        Box::new(UdpTransport::new().map_err(|err| format!("UDP socket: {}", err))?)
+    };
+    Ok(transport)
 }
 
 fn main() {
     let options = Options::parse();
-    let dns_client = client::DnsClient::new();
-    dns_client.ask(&options.host, &options.dns_server, 10);
+    let transport = transport(&options).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    let mut dns_client = DnsClient::with_transport(transport);
+    dns_client.set_config(ResolverConfig {
+        timeout: Duration::from_secs(options.timeout),
+        attempts: options.attempts,
+        ..ResolverConfig::default()
+    });
+    dns_client.set_recursion_desired(options.rd || options.tls || options.https || options.quic);
+    if options.no_edns {
+        dns_client.set_edns(None);
+    } else {
+        dns_client.set_edns(Some(Edns {
+            udp_payload_size: options.bufsize,
+            dnssec_ok: options.dnssec,
+            ..Edns::default()
+        }));
+        if options.nsid {
+            dns_client.add_edns_option(EdnsOption::Nsid(vec![]));
+        }
+        if let Some(subnet) = options.subnet {
+            dns_client.add_edns_option(subnet);
+        }
+        if options.cookie {
+            dns_client.add_edns_option(EdnsOption::Cookie {
+                client: rand::random(),
+                server: None,
+            });
+        }
+        dns_client.set_padding(options.padding);
+    }
+    let lookup = dns_client.ask(
+        &options.host,
+        options.q_type,
+        options.q_class,
+        &options.dns_server,
+    );
+    match lookup {
+        Ok(lookup) if options.unicode => println!("{:#}", lookup.response),
+        Ok(lookup) => println!("{}", lookup.response),
+        Err(err) => {
+            eprintln!("{}: {}", options.host, err);
+            process::exit(1);
+        }
+    }
 }
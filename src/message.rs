@@ -0,0 +1,686 @@
+use std::fmt;
+use std::num::NonZeroU16;
+
+use crate::edns::{Edns, EdnsOption};
+use crate::encoder::Encoder;
+use crate::error::{EncodeError, ParseError};
+use crate::header::{Flag, Header, Rcode};
+use crate::name::Name;
+use crate::question::Question;
+use crate::rr::ResourceRecord;
+use crate::types::{Class, RecordType};
+
+/// Largest message carried over UDP without EDNS (RFC 1035)
+pub const MAX_UDP_MESSAGE_SIZE: usize = 512;
+
+/// Largest message that fits the two-octet length prefix used over TCP
+pub const MAX_MESSAGE_SIZE: usize = 65535;
+
+/// DNS message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsMessage {
+    /// DNS header
+    pub header: Header,
+    /// DNS question section
+    pub questions: Vec<Question>,
+    /// DNS answer section
+    pub answers: Vec<ResourceRecord>,
+    /// DNS authority section
+    pub authorities: Vec<ResourceRecord>,
+    /// DNS additional section, without the OPT pseudo-record
+    pub additionals: Vec<ResourceRecord>,
+    /// EDNS information, carried as an OPT record in the additional section
+    pub edns: Option<Edns>,
+}
+
+/// Builder for a query message
+pub struct QueryBuilder {
+    /// Domain name asked for
+    name: Name,
+    /// Type of the records asked for
+    q_type: RecordType,
+    /// Class of the records asked for
+    q_class: Class,
+    /// Whether the server should pursue the query recursively
+    recursion_desired: bool,
+    /// Identifier of the query, random unless set
+    id: Option<u16>,
+    /// EDNS information sent along with the query
+    edns: Option<Edns>,
+}
+
+impl QueryBuilder {
+    /// Set the type of the records asked for, A by default
+    pub fn qtype(mut self, q_type: RecordType) -> QueryBuilder {
+        self.q_type = q_type;
+        self
+    }
+
+    /// Set the class of the records asked for, IN by default
+    pub fn class(mut self, q_class: Class) -> QueryBuilder {
+        self.q_class = q_class;
+        self
+    }
+
+    /// Ask the server to resolve the query recursively, off by default
+    pub fn recursion_desired(mut self, recursion_desired: bool) -> QueryBuilder {
+        self.recursion_desired = recursion_desired;
+        self
+    }
+
+    /// Set the identifier of the query instead of a random one
+    pub fn id(mut self, id: u16) -> QueryBuilder {
+        self.id = Some(id);
+        self
+    }
+
+    /// Attach EDNS information to the query
+    pub fn edns(mut self, edns: Edns) -> QueryBuilder {
+        self.edns = Some(edns);
+        self
+    }
+
+    /// Build the query message
+    pub fn build(self) -> DnsMessage {
+        let mut dns_flags = Flag::default();
+        dns_flags.set_rd(self.recursion_desired);
+
+        let dns_header = Header {
+            id: self.id.unwrap_or_else(rand::random::<u16>),
+            flags: dns_flags,
+            qd_cnt: 1,
+            an_cnt: 0,
+            ns_cnt: 0,
+            ar_cnt: self.edns.is_some() as u16,
+        };
+
+        let dns_question = Question {
+            q_name: self.name,
+            q_type: self.q_type,
+            q_class: self.q_class,
+        };
+
+        DnsMessage {
+            header: dns_header,
+            questions: vec![dns_question],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+            edns: self.edns,
+        }
+    }
+}
+
+impl DnsMessage {
+    /// Create a new DNS message asking for the IPv4 addresses of a host
+    pub fn new(name: Name) -> DnsMessage {
+        DnsMessage::query(name).build()
+    }
+
+    /// Start building a query for a domain name
+    pub fn query(name: Name) -> QueryBuilder {
+        QueryBuilder {
+            name,
+            q_type: RecordType::A,
+            q_class: Class::IN,
+            recursion_desired: false,
+            id: None,
+            edns: None,
+        }
+    }
+
+    /// Transform a dns message to a vector of bytes, compressing names
+    pub fn to_be_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        self.encode(true)
+    }
+
+    /// Transform a dns message to a vector of bytes. Compression can be turned
+    /// off to produce the canonical form required for DNSSEC
+    pub fn encode(&self, compress: bool) -> Result<Vec<u8>, EncodeError> {
+        let mut encoder = Encoder::new(compress);
+
+        encoder.put_bytes(&self.counted_header()?.to_be_bytes());
+        for question in &self.questions {
+            question.encode(&mut encoder);
+        }
+
+        for rr in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            rr.encode(&mut encoder)?;
+        }
+        if let Some(edns) = &self.edns {
+            edns.to_record().encode(&mut encoder)?;
+        }
+
+        Ok(encoder.into_bytes())
+    }
+
+    /// Header whose counts match the sections of the message
+    pub(crate) fn counted_header(&self) -> Result<Header, EncodeError> {
+        let [qd_cnt, an_cnt, ns_cnt, ar_cnt] = self.section_counts();
+        Ok(Header {
+            qd_cnt: section_count(qd_cnt)?,
+            an_cnt: section_count(an_cnt)?,
+            ns_cnt: section_count(ns_cnt)?,
+            ar_cnt: section_count(ar_cnt)?,
+            ..self.header.clone()
+        })
+    }
+
+    /// Number of entries in each section, the OPT record of EDNS included
+    fn section_counts(&self) -> [usize; 4] {
+        [
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            self.additionals.len() + self.edns.is_some() as usize,
+        ]
+    }
+
+    /// The question of the message, for the common case of a message with a
+    /// single question
+    pub fn question(&self) -> Option<&Question> {
+        self.questions.first()
+    }
+
+    /// Mutable access to the first question of the message
+    pub fn question_mut(&mut self) -> Option<&mut Question> {
+        self.questions.first_mut()
+    }
+
+    /// Largest response a server may send back over UDP to this query
+    pub fn max_response_size(&self) -> usize {
+        match &self.edns {
+            Some(edns) => MAX_UDP_MESSAGE_SIZE.max(edns.udp_payload_size as usize),
+            None => MAX_UDP_MESSAGE_SIZE,
+        }
+    }
+
+    /// Pad the message with an EDNS padding option so that its length is a
+    /// multiple of `block_size` (RFC 7830, RFC 8467). Messages without EDNS
+    /// are left untouched
+    pub fn pad(&mut self, block_size: NonZeroU16) {
+        let Some(edns) = self.edns.as_mut() else {
+            return;
+        };
+        edns.options
+            .retain(|option| !matches!(option, EdnsOption::Padding(_)));
+        edns.options.push(EdnsOption::Padding(0));
+
+        // A message that can't be encoded is left for its sender to reject
+        let Ok(bytes) = self.to_be_bytes() else {
+            return;
+        };
+        let block_size = usize::from(block_size.get());
+        let padding = (block_size - bytes.len() % block_size) % block_size;
+        if let Some(edns) = self.edns.as_mut() {
+            edns.options.pop();
+            // Less than the block size, which fits in 16 bits
+            edns.options.push(EdnsOption::Padding(padding as u16));
+        }
+    }
+
+    /// Full response code, combining the header bits with the extended bits
+    /// carried by EDNS
+    pub fn rcode(&self) -> Rcode {
+        let extended = self.edns.as_ref().map_or(0, |edns| edns.extended_rcode);
+        Rcode::from((extended as u16) << 4 | u16::from(self.header.flags.rcode()))
+    }
+
+    /// Parse a vector of bytes into a DNS message
+    pub fn parse(message: &[u8]) -> Result<DnsMessage, ParseError> {
+        let mut start = 0;
+        let parsed_value = Header::parse(message, start)?;
+        start = parsed_value.0;
+        let header = parsed_value.1;
+
+        let mut questions = vec![];
+        for _ in 0..header.qd_cnt {
+            let question = Question::parse(message, start)?;
+            questions.push(question.1);
+            start = question.0;
+        }
+
+        // OPT records belong in the additional section only (RFC 6891
+        // section 6.1.1)
+        let mut answers = vec![];
+        for _ in 0..header.an_cnt {
+            let answer = ResourceRecord::parse(message, start)?;
+            if answer.1.an_type == RecordType::OPT {
+                return Err(ParseError::InvalidOpt { offset: start });
+            }
+            answers.push(answer.1);
+            start = answer.0;
+        }
+
+        let mut authorities = vec![];
+        for _ in 0..header.ns_cnt {
+            let authority = ResourceRecord::parse(message, start)?;
+            if authority.1.an_type == RecordType::OPT {
+                return Err(ParseError::InvalidOpt { offset: start });
+            }
+            authorities.push(authority.1);
+            start = authority.0;
+        }
+
+        let mut additionals = vec![];
+        let mut edns = None;
+        for _ in 0..header.ar_cnt {
+            let additional = ResourceRecord::parse(message, start)?;
+            if additional.1.an_type == RecordType::OPT {
+                if edns.is_some() {
+                    return Err(ParseError::InvalidOpt { offset: start });
+                }
+                edns = Some(Edns::parse(&additional.1, start)?);
+            } else {
+                additionals.push(additional.1);
+            }
+            start = additional.0;
+        }
+
+        if start != message.len() {
+            return Err(ParseError::TrailingBytes { offset: start });
+        }
+
+        let dns_message = DnsMessage {
+            header,
+            questions,
+            answers,
+            authorities,
+            additionals,
+            edns,
+        };
+
+        Ok(dns_message)
+    }
+}
+
+/// Count of a section as carried in the header
+pub(crate) fn section_count(count: usize) -> Result<u16, EncodeError> {
+    u16::try_from(count).map_err(|_| EncodeError::SectionTooLong { count })
+}
+
+impl fmt::Display for DnsMessage {
+    /// Write the message the way dig shows it: a commented header followed
+    /// by each non-empty section in master file format. The alternate form
+    /// `{:#}` shows names with U-labels
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [qd_cnt, an_cnt, ns_cnt, ar_cnt] = self.section_counts();
+        writeln!(
+            f,
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            self.header.flags.opcode(),
+            self.rcode(),
+            self.header.id
+        )?;
+        write!(
+            f,
+            ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.header.flags, qd_cnt, an_cnt, ns_cnt, ar_cnt
+        )?;
+
+        if let Some(edns) = &self.edns {
+            write!(f, "\n\n;; OPT PSEUDOSECTION:\n{}", edns)?;
+        }
+        if !self.questions.is_empty() {
+            write!(f, "\n\n;; QUESTION SECTION:")?;
+            for question in &self.questions {
+                write!(f, "\n;")?;
+                question.fmt(f)?;
+            }
+        }
+        let sections = [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authorities),
+            ("ADDITIONAL", &self.additionals),
+        ];
+        for (section, rrs) in sections {
+            if rrs.is_empty() {
+                continue;
+            }
+            write!(f, "\n\n;; {} SECTION:", section)?;
+            for rr in rrs {
+                writeln!(f)?;
+                rr.fmt(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::Opcode;
+    use crate::rdata::RData;
+    use crate::strategies;
+    use proptest::prelude::*;
+    use std::net::Ipv4Addr;
+
+    fn name(text: &str) -> Name {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_dns_response() {
+        let response_bytes = vec![
+            0x00, 0x16, 0x80, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x03, 0x64,
+            0x6e, 0x73, 0x06, 0x67, 0x6f, 0x6f, 0x67, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00,
+            0x00, 0x01, 0x00, 0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x14,
+            0x00, 0x04, 0x08, 0x08, 0x08, 0x08, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+            0x02, 0x14, 0x00, 0x04, 0x08, 0x08, 0x04, 0x04,
+        ];
+
+        let dns_response = DnsMessage::parse(&response_bytes).unwrap();
+        let q_name = dns_response.questions[0].q_name.to_string();
+        assert_eq!(q_name, "dns.google.com.");
+        let answers = dns_response.answers;
+        assert_eq!(answers.len(), 2);
+        for answer in &answers {
+            assert_eq!(answer.an_name, name("dns.google.com"));
+        }
+        assert_eq!(answers[0].an_rdata, RData::A(Ipv4Addr::new(8, 8, 8, 8)));
+        assert_eq!(answers[1].an_rdata, RData::A(Ipv4Addr::new(8, 8, 4, 4)));
+    }
+
+    #[test]
+    fn compress_response() {
+        let dns_google = name("dns.google.com");
+        let mut dns_msg = DnsMessage::new(dns_google.clone());
+        for ip in [[8, 8, 8, 8], [8, 8, 4, 4]] {
+            dns_msg.answers.push(ResourceRecord {
+                an_name: dns_google.clone(),
+                an_type: RecordType::A,
+                an_class: Class::IN,
+                an_ttl: 532,
+                an_rdata: RData::A(Ipv4Addr::from(ip)),
+            });
+        }
+
+        let compressed = dns_msg.to_be_bytes().unwrap();
+        let uncompressed = dns_msg.encode(false).unwrap();
+        assert_eq!(compressed.len(), 64);
+        assert_eq!(
+            uncompressed.len(),
+            64 + 2 * (dns_google.as_wire().len() - 2)
+        );
+        assert_eq!(compressed[32..34], [0xC0, 0x0C]);
+
+        for bytes in [compressed, uncompressed] {
+            let parsed = DnsMessage::parse(&bytes).unwrap();
+            assert_eq!(parsed.answers.len(), 2);
+            assert_eq!(parsed.answers[1].an_name, dns_google);
+            assert_eq!(
+                parsed.answers[1].an_rdata,
+                RData::A(Ipv4Addr::new(8, 8, 4, 4))
+            );
+        }
+    }
+
+    #[test]
+    fn build_query() {
+        let dns_msg = DnsMessage::query(name("example.com"))
+            .qtype(RecordType::MX)
+            .class(Class::CH)
+            .recursion_desired(true)
+            .id(0x1234)
+            .build();
+
+        assert_eq!(dns_msg.header.id, 0x1234);
+        assert!(dns_msg.header.flags.rd());
+        assert!(!dns_msg.header.flags.qr());
+        assert_eq!(dns_msg.questions[0].q_type, RecordType::MX);
+        assert_eq!(dns_msg.questions[0].q_class, Class::CH);
+        assert_eq!(
+            dns_msg.to_be_bytes().unwrap()[..4],
+            [0x12, 0x34, 0x01, 0x00]
+        );
+        assert!(dns_msg.to_be_bytes().unwrap().ends_with(&[0, 0, 15, 0, 3]));
+    }
+
+    #[test]
+    fn new_asks_for_ipv4_addresses() {
+        let dns_msg = DnsMessage::new(name("example.com"));
+        assert_eq!(dns_msg.questions[0].q_type, RecordType::A);
+        assert_eq!(dns_msg.questions[0].q_class, Class::IN);
+        assert!(!dns_msg.header.flags.rd());
+    }
+
+    #[test]
+    fn encode_long_query() {
+        let label = "a".repeat(63);
+        let address = [label.as_str(); 3].join(".");
+        let bytes = DnsMessage::new(name(&address)).to_be_bytes().unwrap();
+        assert_eq!(bytes.len(), 12 + 3 * 64 + 1 + 4);
+        assert_eq!(
+            DnsMessage::parse(&bytes).unwrap().questions[0]
+                .q_name
+                .as_wire()
+                .len(),
+            193
+        );
+    }
+
+    #[test]
+    fn round_trip_edns_query() {
+        let edns = Edns {
+            dnssec_ok: true,
+            ..Edns::default()
+        };
+        let dns_msg = DnsMessage::query(name("example.com")).edns(edns).build();
+        assert_eq!(dns_msg.header.ar_cnt, 1);
+        assert_eq!(dns_msg.max_response_size(), 1232);
+
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert!(bytes.ends_with(&[0, 0, 41, 0x04, 0xD0, 0, 0, 0x80, 0, 0, 0]));
+        let parsed = DnsMessage::parse(&bytes).unwrap();
+        assert!(parsed.additionals.is_empty());
+        assert_eq!(parsed, dns_msg);
+    }
+
+    #[test]
+    fn reject_oversized_section() {
+        let mut dns_msg = DnsMessage::query(name("example.com"))
+            .edns(Edns::default())
+            .build();
+        let question = dns_msg.questions[0].clone();
+        dns_msg.questions = vec![question; 65536];
+        assert_eq!(
+            dns_msg.to_be_bytes(),
+            Err(EncodeError::SectionTooLong { count: 65536 })
+        );
+        assert!(dns_msg.to_string().contains("QUERY: 65536,"));
+    }
+
+    #[test]
+    fn pad_to_block_size() {
+        let mut dns_msg = DnsMessage::query(name("example.com"))
+            .edns(Edns::default())
+            .build();
+        let block_size = |size| NonZeroU16::new(size).unwrap();
+        dns_msg.pad(block_size(128));
+        assert_eq!(dns_msg.to_be_bytes().unwrap().len(), 128);
+        dns_msg.pad(block_size(468));
+        assert_eq!(dns_msg.to_be_bytes().unwrap().len(), 468);
+        dns_msg.pad(block_size(1));
+        assert_eq!(dns_msg.to_be_bytes().unwrap().len(), 44);
+        dns_msg.pad(block_size(u16::MAX));
+        assert_eq!(dns_msg.to_be_bytes().unwrap().len(), 65535);
+        assert_eq!(dns_msg.edns.unwrap().options.len(), 1);
+    }
+
+    #[test]
+    fn combine_extended_rcode() {
+        let mut dns_msg = DnsMessage::query(name("example.com"))
+            .edns(Edns::default())
+            .build();
+        dns_msg.header.flags.set_rcode(Rcode::NoError);
+        dns_msg.edns.as_mut().unwrap().extended_rcode = 1;
+        assert_eq!(dns_msg.rcode(), Rcode::BadVers);
+    }
+
+    #[test]
+    fn reject_duplicate_opt() {
+        let mut dns_msg = DnsMessage::query(name("example.com"))
+            .edns(Edns::default())
+            .build();
+        dns_msg.additionals.push(Edns::default().to_record());
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert_eq!(
+            DnsMessage::parse(&bytes),
+            Err(ParseError::InvalidOpt { offset: 40 })
+        );
+    }
+
+    #[test]
+    fn reject_opt_outside_additional_section() {
+        let mut dns_msg = DnsMessage::query(name("example.com")).build();
+        dns_msg.answers.push(Edns::default().to_record());
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert_eq!(
+            DnsMessage::parse(&bytes),
+            Err(ParseError::InvalidOpt { offset: 29 })
+        );
+
+        let mut dns_msg = DnsMessage::query(name("example.com")).build();
+        dns_msg.authorities.push(Edns::default().to_record());
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert_eq!(
+            DnsMessage::parse(&bytes),
+            Err(ParseError::InvalidOpt { offset: 29 })
+        );
+    }
+
+    #[test]
+    fn round_trip_question_counts() {
+        let mut dns_msg = DnsMessage::new(name("example.com"));
+        dns_msg.header.qd_cnt = 7;
+        dns_msg.questions.push(dns_msg.questions[0].clone());
+        dns_msg.questions[1].q_type = RecordType::AAAA;
+        dns_msg.answers.push(ResourceRecord {
+            an_name: dns_msg.questions[0].q_name.clone(),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 60,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert_eq!(bytes[4..8], [0, 2, 0, 1]);
+        let parsed = DnsMessage::parse(&bytes).unwrap();
+        assert_eq!(parsed.questions, dns_msg.questions);
+        assert_eq!(parsed.answers, dns_msg.answers);
+        assert_eq!(parsed.question().unwrap().q_type, RecordType::A);
+    }
+
+    #[test]
+    fn parse_message_without_question() {
+        let mut dns_msg = DnsMessage::new(name("example.com"));
+        dns_msg.questions.clear();
+        dns_msg.header.flags.set_opcode(Opcode::Notify);
+
+        let bytes = dns_msg.to_be_bytes().unwrap();
+        assert_eq!(bytes.len(), 12);
+        let parsed = DnsMessage::parse(&bytes).unwrap();
+        assert!(parsed.question().is_none());
+        assert_eq!(parsed.header.qd_cnt, 0);
+    }
+
+    #[test]
+    fn reject_trailing_bytes() {
+        let mut bytes = DnsMessage::new(name("dns.google.com"))
+            .to_be_bytes()
+            .unwrap();
+        let len = bytes.len();
+        bytes.push(0);
+        assert_eq!(
+            DnsMessage::parse(&bytes).err(),
+            Some(ParseError::TrailingBytes { offset: len })
+        );
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        let response_bytes = vec![
+            0x00, 0x16, 0x80, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x03, 0x64,
+            0x6e, 0x73, 0x06, 0x67, 0x6f, 0x6f, 0x67, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00,
+            0x00, 0x01, 0x00, 0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x14,
+            0x00, 0x04, 0x08, 0x08, 0x08, 0x08, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+            0x02, 0x14, 0x00, 0x04, 0x08, 0x08, 0x04, 0x04,
+        ];
+
+        for len in 0..response_bytes.len() {
+            assert!(DnsMessage::parse(&response_bytes[..len]).is_err());
+        }
+        for i in 0..response_bytes.len() {
+            for value in 0..=u8::MAX {
+                let mut bytes = response_bytes.clone();
+                bytes[i] = value;
+                let _ = DnsMessage::parse(&bytes);
+            }
+        }
+        for _ in 0..10000 {
+            let len = rand::random::<usize>() % 512;
+            let bytes = (0..len).map(|_| rand::random::<u8>()).collect::<Vec<u8>>();
+            let _ = DnsMessage::parse(&bytes);
+        }
+    }
+
+    #[test]
+    fn display_like_dig() {
+        let edns = Edns {
+            dnssec_ok: true,
+            options: vec![EdnsOption::Nsid(b"ns1".to_vec())],
+            ..Edns::default()
+        };
+        let mut dns_msg = DnsMessage::query(name("bücher.example"))
+            .recursion_desired(true)
+            .id(0x1234)
+            .edns(edns)
+            .build();
+        dns_msg.header.flags.set_qr(true);
+        dns_msg.header.flags.set_ra(true);
+        dns_msg.answers.push(ResourceRecord {
+            an_name: name("bücher.example"),
+            an_type: RecordType::A,
+            an_class: Class::IN,
+            an_ttl: 300,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+
+        let expected = "\
+;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660
+;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1
+
+;; OPT PSEUDOSECTION:
+; EDNS: version: 0, flags: do; udp: 1232
+; NSID: 6e7331 (\"ns1\")
+
+;; QUESTION SECTION:
+;xn--bcher-kva.example.\tIN\tA
+
+;; ANSWER SECTION:
+xn--bcher-kva.example.\t300\tIN\tA\t192.0.2.1";
+        assert_eq!(dns_msg.to_string(), expected);
+        assert!(format!("{:#}", dns_msg).ends_with("\nbücher.example.\t300\tIN\tA\t192.0.2.1"));
+    }
+
+    proptest! {
+        #[test]
+        fn message_round_trip(dns_msg in strategies::message()) {
+            prop_assert_eq!(DnsMessage::parse(&dns_msg.to_be_bytes().unwrap()), Ok(dns_msg.clone()));
+            prop_assert_eq!(DnsMessage::parse(&dns_msg.encode(false).unwrap()), Ok(dns_msg));
+        }
+
+        #[test]
+        fn parse_arbitrary_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            if let Ok(dns_msg) = DnsMessage::parse(&bytes) {
+                prop_assert_eq!(DnsMessage::parse(&dns_msg.to_be_bytes().unwrap()), Ok(dns_msg));
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use std::fmt;
+
+use crate::edns::Edns;
+use crate::error::ParseError;
+use crate::header::Header;
+use crate::message::DnsMessage;
+use crate::name::{self, Name};
+use crate::question::Question;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::{Class, RecordType};
+use crate::utility;
+
+/// Read-only view of a DNS message that borrows from the received bytes.
+/// Only the header is parsed up front, sections are walked and names are
+/// decoded when asked for
+#[derive(Debug, Clone)]
+pub struct DnsMessageRef<'a> {
+    /// Bytes of the whole message
+    message: &'a [u8],
+    /// DNS header
+    pub header: Header,
+}
+
+/// Domain name inside a message, decoded on demand
+#[derive(Debug, Clone, Copy)]
+pub struct NameRef<'a> {
+    /// Bytes of the whole message
+    message: &'a [u8],
+    /// Position of the name in the message
+    offset: usize,
+}
+
+/// Question inside a message
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionRef<'a> {
+    /// Domain name
+    pub q_name: NameRef<'a>,
+    /// Type of query
+    pub q_type: RecordType,
+    /// Class of query
+    pub q_class: Class,
+}
+
+/// Resource record inside a message
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    /// Bytes of the whole message
+    message: &'a [u8],
+    /// Position of the rdata in the message
+    rdata_offset: usize,
+    /// A domain name to which this resource record pertains
+    pub an_name: NameRef<'a>,
+    /// RR type codes specifying the meaning in rdata
+    pub an_type: RecordType,
+    /// Class of the data in rdata
+    pub an_class: Class,
+    /// Time interval in seconds
+    pub an_ttl: u32,
+    /// Raw rdata, whose names may be compressed against the message
+    pub an_rdata: &'a [u8],
+}
+
+/// Iterator over the questions of a message
+pub struct Questions<'a> {
+    message: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+/// Iterator over the resource records of a section
+pub struct Records<'a> {
+    message: &'a [u8],
+    pos: Result<usize, ParseError>,
+    remaining: u16,
+}
+
+/// Iterator over the labels of a name
+pub struct Labels<'a> {
+    message: &'a [u8],
+    pos: usize,
+    limit: usize,
+    len: usize,
+    done: bool,
+}
+
+impl<'a> DnsMessageRef<'a> {
+    /// Create a view of a message, parsing only its header
+    pub fn parse(message: &'a [u8]) -> Result<DnsMessageRef<'a>, ParseError> {
+        let (_, header) = Header::parse(message, 0)?;
+        Ok(DnsMessageRef { message, header })
+    }
+
+    /// Bytes of the whole message
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.message
+    }
+
+    /// Iterate over the question section
+    pub fn questions(&self) -> Questions<'a> {
+        Questions {
+            message: self.message,
+            pos: 12,
+            remaining: self.header.qd_cnt,
+        }
+    }
+
+    /// Iterate over the answer section
+    pub fn answers(&self) -> Records<'a> {
+        let pos = self.questions_end();
+        self.records(pos, self.header.an_cnt)
+    }
+
+    /// Iterate over the authority section
+    pub fn authorities(&self) -> Records<'a> {
+        let pos = self
+            .questions_end()
+            .and_then(|pos| Records::skip(self.message, pos, self.header.an_cnt));
+        self.records(pos, self.header.ns_cnt)
+    }
+
+    /// Iterate over the additional section, including any OPT record
+    pub fn additionals(&self) -> Records<'a> {
+        let pos = self
+            .questions_end()
+            .and_then(|pos| Records::skip(self.message, pos, self.header.an_cnt))
+            .and_then(|pos| Records::skip(self.message, pos, self.header.ns_cnt));
+        self.records(pos, self.header.ar_cnt)
+    }
+
+    /// EDNS information from the OPT record of the additional section
+    pub fn edns(&self) -> Result<Option<Edns>, ParseError> {
+        for record in self.additionals() {
+            let record = record?;
+            if record.an_type == RecordType::OPT {
+                let offset = record.an_name.offset;
+                return Edns::parse(&record.to_owned()?, offset).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the whole message into an owned message
+    pub fn to_owned(&self) -> Result<DnsMessage, ParseError> {
+        DnsMessage::parse(self.message)
+    }
+
+    fn records(&self, pos: Result<usize, ParseError>, count: u16) -> Records<'a> {
+        Records {
+            message: self.message,
+            pos,
+            remaining: count,
+        }
+    }
+
+    /// Position right after the question section
+    fn questions_end(&self) -> Result<usize, ParseError> {
+        let mut pos = 12;
+        for _ in 0..self.header.qd_cnt {
+            pos = name::skip(self.message, pos)? + 4;
+        }
+        if pos > self.message.len() {
+            return Err(ParseError::Truncated { offset: pos - 4 });
+        }
+        Ok(pos)
+    }
+}
+
+impl<'a> NameRef<'a> {
+    /// Position of the name in the message
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Iterate over the labels of the name, following compression pointers
+    pub fn labels(&self) -> Labels<'a> {
+        Labels {
+            message: self.message,
+            pos: self.offset,
+            limit: self.offset,
+            len: 0,
+            done: false,
+        }
+    }
+
+    /// Decode into an owned name
+    pub fn to_name(&self) -> Result<Name, ParseError> {
+        Name::parse(self.message, self.offset).map(|(_, name)| name)
+    }
+
+    /// Compare with a name without decoding it first, ignoring ASCII case
+    pub fn eq_name(&self, other: &Name) -> bool {
+        let other = other.as_wire();
+        let mut pos = 0;
+        for label in self.labels() {
+            let Ok(label) = label else {
+                return false;
+            };
+            match other.get(pos..pos + 1 + label.len()) {
+                Some(bytes) if bytes[0] as usize == label.len() => {
+                    if !bytes[1..].eq_ignore_ascii_case(label) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+            pos += 1 + label.len();
+        }
+        other.get(pos..) == Some(&[0])
+    }
+}
+
+impl fmt::Display for NameRef<'_> {
+    /// Write the name in presentation format as [`Name`] does, or as much of
+    /// it as could be decoded
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut root = true;
+        for label in self.labels() {
+            let Ok(label) = label else {
+                return write!(f, "<invalid>");
+            };
+            name::write_label(f, label)?;
+            write!(f, ".")?;
+            root = false;
+        }
+        if root {
+            write!(f, ".")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = Result<&'a [u8], ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_label();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+impl<'a> Labels<'a> {
+    /// Read the next non-root label, applying the same checks as
+    /// `name::parse`
+    fn next_label(&mut self) -> Result<Option<&'a [u8]>, ParseError> {
+        let start = self.limit;
+        loop {
+            let pos = self.pos;
+            let len = *self
+                .message
+                .get(pos)
+                .ok_or(ParseError::Truncated { offset: pos })?;
+            match len & 0xC0 {
+                0x00 => {
+                    let len = len as usize;
+                    let label = self
+                        .message
+                        .get(pos + 1..pos + 1 + len)
+                        .ok_or(ParseError::Truncated { offset: pos })?;
+                    self.len += 1 + len;
+                    if self.len > name::MAX_NAME_LENGTH {
+                        return Err(ParseError::NameTooLong { offset: start });
+                    }
+                    self.pos = pos + 1 + len;
+                    return Ok((len > 0).then_some(label));
+                }
+                0xC0 => {
+                    let low = *self
+                        .message
+                        .get(pos + 1)
+                        .ok_or(ParseError::Truncated { offset: pos })?;
+                    let offset = ((len as usize & 0x3F) << 8) | low as usize;
+                    if offset >= pos {
+                        return Err(ParseError::ForwardPointer { offset: pos });
+                    }
+                    if offset >= self.limit {
+                        return Err(ParseError::PointerLoop { offset: pos });
+                    }
+                    self.limit = offset;
+                    self.pos = offset;
+                }
+                _ => {
+                    return Err(ParseError::BadLabelLength {
+                        offset: pos,
+                        length: len,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl<'a> QuestionRef<'a> {
+    /// Decode into an owned question
+    pub fn to_owned(&self) -> Result<Question, ParseError> {
+        Ok(Question {
+            q_name: self.q_name.to_name()?,
+            q_type: self.q_type,
+            q_class: self.q_class,
+        })
+    }
+}
+
+impl<'a> RecordRef<'a> {
+    /// Decode the rdata into its typed form
+    pub fn rdata(&self) -> Result<RData, ParseError> {
+        RData::parse(
+            self.message,
+            self.rdata_offset,
+            self.an_rdata.len(),
+            self.an_type,
+        )
+    }
+
+    /// Decode into an owned resource record
+    pub fn to_owned(&self) -> Result<ResourceRecord, ParseError> {
+        Ok(ResourceRecord {
+            an_name: self.an_name.to_name()?,
+            an_type: self.an_type,
+            an_class: self.an_class,
+            an_ttl: self.an_ttl,
+            an_rdata: self.rdata()?,
+        })
+    }
+}
+
+impl<'a> Iterator for Questions<'a> {
+    type Item = Result<QuestionRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let result = (|| {
+            let end = name::skip(self.message, self.pos)?;
+            let question = QuestionRef {
+                q_name: NameRef {
+                    message: self.message,
+                    offset: self.pos,
+                },
+                q_type: RecordType::from(utility::read_u16(self.message, end)?),
+                q_class: Class::from(utility::read_u16(self.message, end + 2)?),
+            };
+            self.pos = end + 4;
+            Ok(question)
+        })();
+        self.remaining = if result.is_ok() {
+            self.remaining - 1
+        } else {
+            0
+        };
+        Some(result)
+    }
+}
+
+impl<'a> Records<'a> {
+    /// Skip `count` records starting at `pos`
+    fn skip(message: &[u8], mut pos: usize, count: u16) -> Result<usize, ParseError> {
+        for _ in 0..count {
+            let end = name::skip(message, pos)?;
+            let rdlength = utility::read_u16(message, end + 8)? as usize;
+            pos = end + 10 + rdlength;
+        }
+        if pos > message.len() {
+            return Err(ParseError::Truncated {
+                offset: message.len(),
+            });
+        }
+        Ok(pos)
+    }
+
+    fn read(&mut self, pos: usize) -> Result<RecordRef<'a>, ParseError> {
+        let message = self.message;
+        let end = name::skip(message, pos)?;
+        let rdlength = utility::read_u16(message, end + 8)?;
+        let rdata_offset = end + 10;
+        let an_rdata = message
+            .get(rdata_offset..rdata_offset + rdlength as usize)
+            .ok_or(ParseError::RdLengthOverrun {
+                offset: end + 8,
+                rdlength,
+            })?;
+
+        Ok(RecordRef {
+            message,
+            rdata_offset,
+            an_name: NameRef {
+                message,
+                offset: pos,
+            },
+            an_type: RecordType::from(utility::read_u16(message, end)?),
+            an_class: Class::from(utility::read_u16(message, end + 2)?),
+            an_ttl: utility::read_u32(message, end + 4)?,
+            an_rdata,
+        })
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<RecordRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let result = self.pos.clone().and_then(|pos| self.read(pos));
+        match &result {
+            Ok(record) => {
+                self.pos = Ok(record.rdata_offset + record.an_rdata.len());
+                self.remaining -= 1;
+            }
+            Err(_) => self.remaining = 0,
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+
+    const RESPONSE: [u8; 64] = [
+        0x00, 0x16, 0x80, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x03, 0x64, 0x6e,
+        0x73, 0x06, 0x67, 0x6f, 0x6f, 0x67, 0x6c, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x01,
+        0x00, 0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x14, 0x00, 0x04, 0x08,
+        0x08, 0x08, 0x08, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x14, 0x00, 0x04,
+        0x08, 0x08, 0x04, 0x04,
+    ];
+
+    #[test]
+    fn view_response() {
+        let view = DnsMessageRef::parse(&RESPONSE).unwrap();
+        let question = view.questions().next().unwrap().unwrap();
+        assert_eq!(question.q_name.to_string(), "dns.google.com.");
+        assert_eq!(question.q_type, RecordType::A);
+
+        let answers = view.answers().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[1].an_name.offset(), 48);
+        assert_eq!(answers[1].an_name.to_string(), "dns.google.com.");
+        assert!(answers[1]
+            .an_name
+            .eq_name(&"DNS.google.com".parse().unwrap()));
+        assert!(!answers[1].an_name.eq_name(&"google.com".parse().unwrap()));
+        assert_eq!(answers[1].an_ttl, 532);
+        assert_eq!(answers[1].an_rdata, [8, 8, 4, 4]);
+        assert_eq!(view.authorities().count(), 0);
+        assert_eq!(view.additionals().count(), 0);
+    }
+
+    #[test]
+    fn display_names_as_owned_names() {
+        let names = [
+            Name::from_wire(&[3, b'a', b'.', 0xFF, 4, b'x', b' ', b'"', b';', 0]).unwrap(),
+            "mail.bücher.de".parse().unwrap(),
+            Name::root(),
+        ];
+        for name in names {
+            let bytes = DnsMessage::new(name.clone()).to_be_bytes().unwrap();
+            let view = DnsMessageRef::parse(&bytes).unwrap();
+            let question = view.questions().next().unwrap().unwrap();
+            assert_eq!(question.q_name.to_string(), name.to_string());
+            assert_eq!(format!("{:#}", question.q_name), format!("{:#}", name));
+        }
+    }
+
+    #[test]
+    fn report_errors_once() {
+        let view = DnsMessageRef::parse(&RESPONSE[..60]).unwrap();
+        let answers = view.answers().collect::<Vec<_>>();
+        assert_eq!(answers.len(), 2);
+        assert!(answers[0].is_ok());
+        assert_eq!(
+            answers[1].as_ref().err(),
+            Some(&ParseError::RdLengthOverrun {
+                offset: 58,
+                rdlength: 4
+            })
+        );
+    }
+
+    #[test]
+    fn skip_sections_with_saturated_counts() {
+        let mut message = RESPONSE.to_vec();
+        // ANCOUNT 0xFFFF and NSCOUNT 1, whose sum overflows a u16
+        message[6..12].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x01, 0x00, 0x01]);
+        let view = DnsMessageRef::parse(&message).unwrap();
+        let additionals = view.additionals().collect::<Vec<_>>();
+        assert!(matches!(additionals.as_slice(), [Err(_)]));
+    }
+
+    #[test]
+    fn detect_pointer_loop_in_labels() {
+        let mut message = RESPONSE.to_vec();
+        message[32..34].copy_from_slice(&[0xC0, 0x20]);
+        let view = DnsMessageRef::parse(&message).unwrap();
+        let answer = view.answers().next().unwrap().unwrap();
+        assert_eq!(answer.an_name.to_string(), "<invalid>");
+        assert!(answer.to_owned().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn view_matches_owned_message(dns_msg in strategies::message()) {
+            let bytes = dns_msg.to_be_bytes().unwrap();
+            let view = DnsMessageRef::parse(&bytes).unwrap();
+            prop_assert_eq!(&view.header, &dns_msg.header);
+
+            let questions = view
+                .questions()
+                .map(|question| question.and_then(|question| question.to_owned()))
+                .collect::<Result<Vec<_>, _>>();
+            prop_assert_eq!(questions, Ok(dns_msg.questions.clone()));
+
+            let answers = view
+                .answers()
+                .map(|record| record.and_then(|record| record.to_owned()))
+                .collect::<Result<Vec<_>, _>>();
+            prop_assert_eq!(answers, Ok(dns_msg.answers.clone()));
+
+            let authorities = view
+                .authorities()
+                .map(|record| record.and_then(|record| record.to_owned()))
+                .collect::<Result<Vec<_>, _>>();
+            prop_assert_eq!(authorities, Ok(dns_msg.authorities.clone()));
+
+            prop_assert_eq!(view.additionals().count(), view.header.ar_cnt as usize);
+            prop_assert_eq!(view.edns(), Ok(dns_msg.edns.clone()));
+        }
+    }
+}
@@ -0,0 +1,741 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use idna::uts46::{AsciiDenyList, DnsLength, Hyphens, Uts46};
+
+use crate::error::{NameError, ParseError};
+
+/// Maximum length of a domain name in wire format, including the root label
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// Maximum length of a single label
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// Maximum number of compression pointers followed while decoding one name
+const MAX_POINTERS: usize = 128;
+
+/// Parse a domain name starting at `start`, following compression pointers.
+/// Returns the position right after the name in the message together with
+/// the uncompressed name in wire format
+pub fn parse(message: &[u8], start: usize) -> Result<(usize, Vec<u8>), ParseError> {
+    let mut name = vec![];
+    let mut pos = start;
+    // Pointers must point strictly before the segment currently being read,
+    // which rules out both forward pointers and loops
+    let mut limit = start;
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop {
+        let len = *message
+            .get(pos)
+            .ok_or(ParseError::Truncated { offset: pos })?;
+        match len & 0xC0 {
+            0x00 => {
+                let len = len as usize;
+                if pos + 1 + len > message.len() {
+                    return Err(ParseError::Truncated { offset: pos });
+                }
+                if name.len() + 1 + len > MAX_NAME_LENGTH {
+                    return Err(ParseError::NameTooLong { offset: start });
+                }
+                name.extend_from_slice(&message[pos..pos + 1 + len]);
+                pos += 1 + len;
+                if len == 0 {
+                    return Ok((end.unwrap_or(pos), name));
+                }
+            }
+            0xC0 => {
+                let low = *message
+                    .get(pos + 1)
+                    .ok_or(ParseError::Truncated { offset: pos })?;
+                let offset = ((len as usize & 0x3F) << 8) | low as usize;
+                if offset >= pos {
+                    return Err(ParseError::ForwardPointer { offset: pos });
+                }
+                pointers += 1;
+                if offset >= limit || pointers > MAX_POINTERS {
+                    return Err(ParseError::PointerLoop { offset: pos });
+                }
+                if end.is_none() {
+                    end = Some(pos + 2);
+                }
+                limit = offset;
+                pos = offset;
+            }
+            _ => {
+                return Err(ParseError::BadLabelLength {
+                    offset: pos,
+                    length: len,
+                })
+            }
+        }
+    }
+}
+
+/// Find the end of a domain name starting at `start` without decoding it.
+/// Returns the position right after the name in the message
+pub fn skip(message: &[u8], start: usize) -> Result<usize, ParseError> {
+    let mut pos = start;
+    loop {
+        let len = *message
+            .get(pos)
+            .ok_or(ParseError::Truncated { offset: pos })?;
+        match len & 0xC0 {
+            0x00 if len == 0 => return Ok(pos + 1),
+            0x00 => pos += 1 + len as usize,
+            0xC0 if pos + 2 <= message.len() => return Ok(pos + 2),
+            0xC0 => return Err(ParseError::Truncated { offset: pos }),
+            _ => {
+                return Err(ParseError::BadLabelLength {
+                    offset: pos,
+                    length: len,
+                })
+            }
+        }
+    }
+}
+
+/// Domain name, stored in uncompressed wire format. Comparisons ignore ASCII
+/// case and order names canonically (RFC 4034)
+#[derive(Clone)]
+pub struct Name {
+    wire: Vec<u8>,
+}
+
+impl Name {
+    /// The root name
+    pub fn root() -> Name {
+        Name { wire: vec![0] }
+    }
+
+    /// Parse a possibly compressed name starting at `start` in a message.
+    /// Returns the position right after the name in the message
+    pub fn parse(message: &[u8], start: usize) -> Result<(usize, Name), ParseError> {
+        let (end, wire) = parse(message, start)?;
+        Ok((end, Name { wire }))
+    }
+
+    /// Create a name from its uncompressed wire format
+    pub fn from_wire(wire: &[u8]) -> Result<Name, ParseError> {
+        // A pointer in a standalone name can only point at or after itself,
+        // so compressed names are rejected
+        let (end, name) = Name::parse(wire, 0)?;
+        if end != wire.len() {
+            return Err(ParseError::TrailingBytes { offset: end });
+        }
+        Ok(name)
+    }
+
+    /// Uncompressed wire format of the name
+    pub fn as_wire(&self) -> &[u8] {
+        &self.wire
+    }
+
+    /// Whether this is the root name
+    pub fn is_root(&self) -> bool {
+        self.wire == [0]
+    }
+
+    /// Iterate over the labels of the name from the leftmost one, without the
+    /// empty root label
+    pub fn labels(&self) -> impl DoubleEndedIterator<Item = &[u8]> + '_ {
+        let mut labels = vec![];
+        let mut i = 0;
+        while self.wire[i] != 0 {
+            let len = self.wire[i] as usize;
+            labels.push(&self.wire[i + 1..i + 1 + len]);
+            i += 1 + len;
+        }
+        labels.into_iter()
+    }
+
+    /// Number of labels, without the root label
+    pub fn label_count(&self) -> usize {
+        self.labels().count()
+    }
+
+    /// Name with the leftmost label removed, or `None` for the root
+    pub fn parent(&self) -> Option<Name> {
+        if self.is_root() {
+            return None;
+        }
+        let len = self.wire[0] as usize;
+        Some(Name {
+            wire: self.wire[1 + len..].to_vec(),
+        })
+    }
+
+    /// Name with `label` prepended
+    pub fn child(&self, label: &[u8]) -> Result<Name, NameError> {
+        if label.is_empty() {
+            return Err(NameError::EmptyLabel { position: 0 });
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(NameError::LabelTooLong {
+                position: 0,
+                length: label.len(),
+            });
+        }
+        let length = 1 + label.len() + self.wire.len();
+        if length > MAX_NAME_LENGTH {
+            return Err(NameError::NameTooLong { length });
+        }
+
+        let mut wire = Vec::with_capacity(length);
+        wire.push(label.len() as u8);
+        wire.extend_from_slice(label);
+        wire.extend_from_slice(&self.wire);
+        Ok(Name { wire })
+    }
+
+    /// Name made of the labels of this name followed by those of `suffix`,
+    /// e.g. to make a relative name absolute
+    pub fn join(&self, suffix: &Name) -> Result<Name, NameError> {
+        let mut wire = self.wire[..self.wire.len() - 1].to_vec();
+        wire.extend_from_slice(&suffix.wire);
+        if wire.len() > MAX_NAME_LENGTH {
+            return Err(NameError::NameTooLong { length: wire.len() });
+        }
+        Ok(Name { wire })
+    }
+
+    /// Whether the name is `other` or lies below it
+    pub fn is_subdomain_of(&self, other: &Name) -> bool {
+        let extra = match self.label_count().checked_sub(other.label_count()) {
+            Some(extra) => extra,
+            None => return false,
+        };
+        let mut i = 0;
+        for _ in 0..extra {
+            i += 1 + self.wire[i] as usize;
+        }
+        self.wire[i..].eq_ignore_ascii_case(&other.wire)
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        self.wire.eq_ignore_ascii_case(&other.wire)
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for byte in &self.wire {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+    }
+}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Name) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    /// Canonical order: labels are compared from the rightmost one as
+    /// lowercase byte strings, and a name sorts before its subdomains
+    fn cmp(&self, other: &Name) -> Ordering {
+        let lowercase = |label: &[u8]| label.to_ascii_lowercase();
+        self.labels()
+            .rev()
+            .map(lowercase)
+            .cmp(other.labels().rev().map(lowercase))
+    }
+}
+
+impl FromStr for Name {
+    type Err = NameError;
+
+    /// Parse a name in presentation format, e.g. `www.example.com.`. The
+    /// final dot is optional, and `\.` or `\DDD` escape bytes inside labels.
+    /// Labels written in Unicode are converted to A-labels (IDNA 2008 with
+    /// the UTS 46 mapping)
+    fn from_str(text: &str) -> Result<Name, NameError> {
+        if text == "." {
+            return Ok(Name::root());
+        }
+
+        let bytes = text.as_bytes();
+        let mut wire = vec![];
+        let mut label = vec![];
+        let mut label_start = 0;
+        let mut unicode = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some(len) = separator_len(&bytes[i..]) {
+                push_label(&mut wire, &label, label_start, unicode)?;
+                label.clear();
+                label_start = i + len;
+                unicode = false;
+                i += len;
+                continue;
+            }
+            match bytes[i] {
+                b'\\' => {
+                    if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+                        let value = bytes
+                            .get(i + 1..i + 4)
+                            .filter(|digits| digits.iter().all(u8::is_ascii_digit))
+                            .and_then(|digits| std::str::from_utf8(digits).ok())
+                            .and_then(|digits| digits.parse::<u8>().ok())
+                            .ok_or(NameError::InvalidEscape { position: i })?;
+                        label.push(value);
+                        i += 4;
+                    } else {
+                        let escaped = *bytes
+                            .get(i + 1)
+                            .ok_or(NameError::InvalidEscape { position: i })?;
+                        label.push(escaped);
+                        i += 2;
+                    }
+                }
+                byte => {
+                    label.push(byte);
+                    unicode |= !byte.is_ascii();
+                    i += 1;
+                }
+            }
+        }
+        // The name ends with a dot, unless the last label was left open
+        if !label.is_empty() || label_start == 0 {
+            push_label(&mut wire, &label, label_start, unicode)?;
+        }
+
+        wire.push(0);
+        if wire.len() > MAX_NAME_LENGTH {
+            return Err(NameError::NameTooLong { length: wire.len() });
+        }
+        Ok(Name { wire })
+    }
+}
+
+/// Length of the label separator `text` starts with, if any. Ideographic
+/// and full-width full stops separate labels as a dot does (UTS 46)
+fn separator_len(text: &[u8]) -> Option<usize> {
+    [".", "\u{3002}", "\u{FF0E}", "\u{FF61}"]
+        .into_iter()
+        .find(|separator| text.starts_with(separator.as_bytes()))
+        .map(str::len)
+}
+
+/// Append a label read from text starting at `position`, converting it to
+/// an A-label first if it holds Unicode text
+fn push_label(
+    wire: &mut Vec<u8>,
+    label: &[u8],
+    position: usize,
+    unicode: bool,
+) -> Result<(), NameError> {
+    if label.is_empty() {
+        return Err(NameError::EmptyLabel { position });
+    }
+    let a_label;
+    let label = if unicode {
+        a_label = to_a_label(label, position)?;
+        &a_label
+    } else {
+        label
+    };
+    if label.len() > MAX_LABEL_LENGTH {
+        return Err(NameError::LabelTooLong {
+            position,
+            length: label.len(),
+        });
+    }
+    wire.push(label.len() as u8);
+    wire.extend_from_slice(label);
+    Ok(())
+}
+
+/// Convert a label holding Unicode text into an A-label, e.g. `bücher` into
+/// `xn--bcher-kva`
+fn to_a_label(label: &[u8], position: usize) -> Result<Vec<u8>, NameError> {
+    let invalid = NameError::InvalidIdna { position };
+    let text = std::str::from_utf8(label).map_err(|_| invalid.clone())?;
+    let uts46 = Uts46::new();
+    // Some code points map to a full stop, which can't be part of a label
+    let convert = |label: &[u8], hyphens| {
+        uts46
+            .to_ascii(label, AsciiDenyList::STD3, hyphens, DnsLength::Ignore)
+            .ok()
+            .map(|a_label| a_label.into_owned())
+            .filter(|a_label| !a_label.contains('.'))
+    };
+
+    if let Some(a_label) = convert(label, Hyphens::Check) {
+        return Ok(a_label.into_bytes());
+    }
+    // Point at the first code point that is rejected on its own
+    let code_point = text.chars().find(|c| {
+        let mut buf = [0; 4];
+        convert(c.encode_utf8(&mut buf).as_bytes(), Hyphens::Allow).is_none()
+    });
+    Err(match code_point {
+        Some(code_point) => NameError::DisallowedCodePoint {
+            position,
+            code_point,
+        },
+        None => invalid,
+    })
+}
+
+/// Convert an A-label back into its U-label, or `None` if the label is not a
+/// valid A-label
+fn to_u_label(label: &[u8]) -> Option<String> {
+    if label.len() < 4 || !label[..4].eq_ignore_ascii_case(b"xn--") {
+        return None;
+    }
+    let (u_label, result) = Uts46::new().to_unicode(label, AsciiDenyList::STD3, Hyphens::Check);
+    result.ok().map(|_| u_label.into_owned())
+}
+
+/// Write a byte of a label in presentation format
+fn write_byte(f: &mut fmt::Formatter<'_>, byte: u8) -> fmt::Result {
+    match byte {
+        b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' => {
+            write!(f, "\\{}", byte as char)
+        }
+        0x21..=0x7E => write!(f, "{}", byte as char),
+        _ => write!(f, "\\{:03}", byte),
+    }
+}
+
+/// Write a label in presentation format, as its U-label in the alternate
+/// form `{:#}` if it is an A-label
+pub(crate) fn write_label(f: &mut fmt::Formatter<'_>, label: &[u8]) -> fmt::Result {
+    match to_u_label(label).filter(|_| f.alternate()) {
+        Some(u_label) => {
+            for c in u_label.chars() {
+                match u8::try_from(c) {
+                    Ok(byte) if byte.is_ascii() => write_byte(f, byte)?,
+                    _ => write!(f, "{}", c)?,
+                }
+            }
+        }
+        None => {
+            for &byte in label {
+                write_byte(f, byte)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Name {
+    /// Write the name in presentation format with a final dot, escaping
+    /// bytes so that the text parses back into the same name. The alternate
+    /// form `{:#}` shows A-labels as Unicode U-labels
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return write!(f, ".");
+        }
+        for label in self.labels() {
+            write_label(f, label)?;
+            write!(f, ".")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Name(\"{}\")", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    fn name(text: &str) -> Name {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_uncompressed_name() {
+        let message = vec![3, b'd', b'n', b's', 3, b'c', b'o', b'm', 0, 0xFF];
+        let (end, name) = parse(&message, 0).unwrap();
+        assert_eq!(end, 9);
+        assert_eq!(name, message[0..9]);
+    }
+
+    #[test]
+    fn parse_pointer_in_name_tail() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&[
+            6, b'g', b'o', b'o', b'g', b'l', b'e', 3, b'c', b'o', b'm', 0,
+        ]);
+        message.extend_from_slice(&[3, b'd', b'n', b's', 0xC0, 12]);
+        let (end, name) = parse(&message, 24).unwrap();
+        assert_eq!(end, 30);
+        assert_eq!(Name { wire: name }.to_string(), "dns.google.com.");
+    }
+
+    #[test]
+    fn parse_chained_pointers_with_large_offset() {
+        let mut message = vec![0; 0x140];
+        message.extend_from_slice(&[3, b'c', b'o', b'm', 0]);
+        message.extend_from_slice(&[3, b'f', b'o', b'o', 0xC1, 0x40]);
+        message.extend_from_slice(&[3, b'w', b'w', b'w', 0xC1, 0x45]);
+        let (end, name) = parse(&message, 0x14B).unwrap();
+        assert_eq!(end, 0x151);
+        assert_eq!(Name { wire: name }.to_string(), "www.foo.com.");
+    }
+
+    #[test]
+    fn skip_names() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&[3, b'c', b'o', b'm', 0]);
+        message.extend_from_slice(&[3, b'w', b'w', b'w', 0xC0, 12, 0xFF]);
+        assert_eq!(skip(&message, 12), Ok(17));
+        assert_eq!(skip(&message, 17), Ok(23));
+        assert_eq!(
+            skip(&message[..22], 17),
+            Err(ParseError::Truncated { offset: 21 })
+        );
+    }
+
+    #[test]
+    fn reject_forward_pointer() {
+        let message = vec![0xC0, 0x02, 0x00];
+        assert_eq!(
+            parse(&message, 0),
+            Err(ParseError::ForwardPointer { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn reject_reserved_label_type() {
+        let message = vec![0x40, 0x00];
+        assert_eq!(
+            parse(&message, 0),
+            Err(ParseError::BadLabelLength {
+                offset: 0,
+                length: 0x40
+            })
+        );
+    }
+
+    #[test]
+    fn reject_truncated_name() {
+        let message = vec![3, b'c', b'o', b'm'];
+        assert_eq!(parse(&message, 0), Err(ParseError::Truncated { offset: 4 }));
+        assert_eq!(
+            parse(&message[..3], 0),
+            Err(ParseError::Truncated { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn reject_pointer_loop() {
+        // The label at offset 2 is followed by a pointer back to itself
+        let message = vec![0, 0, 1, b'a', 0xC0, 0x02, 0xC0, 0x04];
+        assert_eq!(
+            parse(&message, 6),
+            Err(ParseError::PointerLoop { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn reject_long_name() {
+        let mut message = vec![];
+        for _ in 0..5 {
+            message.push(63);
+            message.extend_from_slice(&[b'a'; 63]);
+        }
+        message.push(0);
+        assert_eq!(
+            parse(&message, 0),
+            Err(ParseError::NameTooLong { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn parse_text() {
+        let example = name("www.example.com");
+        assert_eq!(example.as_wire(), b"\x03www\x07example\x03com\x00");
+        assert_eq!(name("www.example.com."), example);
+        assert_eq!(name("."), Name::root());
+        assert_eq!(name("a\\.b.c").as_wire(), b"\x03a.b\x01c\x00");
+        assert_eq!(name("\\000\\255x").as_wire(), b"\x03\x00\xFFx\x00");
+        assert_eq!(name("caf\u{e9}").as_wire(), b"\x0bxn--caf-dma\x00");
+    }
+
+    #[test]
+    fn reject_invalid_text() {
+        let long_label = "a".repeat(64);
+        let long_name = vec!["a".repeat(63); 4].join(".");
+        assert_eq!(
+            "".parse::<Name>(),
+            Err(NameError::EmptyLabel { position: 0 })
+        );
+        assert_eq!(
+            "a..b".parse::<Name>(),
+            Err(NameError::EmptyLabel { position: 2 })
+        );
+        assert_eq!(
+            ".a".parse::<Name>(),
+            Err(NameError::EmptyLabel { position: 0 })
+        );
+        assert_eq!(
+            "a\u{3002}\u{FF0E}b".parse::<Name>(),
+            Err(NameError::EmptyLabel { position: 4 })
+        );
+        assert_eq!(
+            format!("x\u{FF61}{}", long_label).parse::<Name>(),
+            Err(NameError::LabelTooLong {
+                position: 4,
+                length: 64
+            })
+        );
+        assert_eq!(
+            format!("x.{}", long_label).parse::<Name>(),
+            Err(NameError::LabelTooLong {
+                position: 2,
+                length: 64
+            })
+        );
+        assert_eq!(
+            long_name.parse::<Name>(),
+            Err(NameError::NameTooLong { length: 257 })
+        );
+        for escape in ["a\\", "a\\25", "a\\256", "a\\1x3"] {
+            assert_eq!(
+                escape.parse::<Name>(),
+                Err(NameError::InvalidEscape { position: 1 })
+            );
+        }
+    }
+
+    #[test]
+    fn display_escapes() {
+        assert_eq!(Name::root().to_string(), ".");
+        assert_eq!(name("a\\.b.com").to_string(), "a\\.b.com.");
+        assert_eq!(name("\\000\\032;.com").to_string(), "\\000\\032\\;.com.");
+    }
+
+    #[test]
+    fn compare_ignoring_case() {
+        let mut names = HashSet::new();
+        names.insert(name("WWW.Example.COM"));
+        assert!(names.contains(&name("www.example.com")));
+        assert_ne!(name("www.example.com"), name("www.example.org"));
+        // Case is still kept as written
+        assert_eq!(name("WWW.Example.COM").to_string(), "WWW.Example.COM.");
+    }
+
+    #[test]
+    fn canonical_order() {
+        // Example of RFC 4034 section 6.1
+        let ordered = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            "zABC.a.EXAMPLE",
+            "z.example",
+            "\\001.z.example",
+            "*.z.example",
+            "\\200.z.example",
+        ]
+        .map(name);
+        let mut sorted = ordered.clone();
+        sorted.reverse();
+        sorted.sort();
+        assert_eq!(sorted, ordered);
+        assert!(Name::root() < ordered[0]);
+    }
+
+    #[test]
+    fn walk_the_tree() {
+        let www = name("www.example.com");
+        let example = www.parent().unwrap();
+        assert_eq!(example, name("example.com"));
+        assert_eq!(example.child(b"www"), Ok(www.clone()));
+        assert_eq!(
+            www.labels().collect::<Vec<_>>(),
+            [&b"www"[..], b"example", b"com"]
+        );
+        assert_eq!(www.label_count(), 3);
+        assert_eq!(name("com").parent(), Some(Name::root()));
+        assert_eq!(Name::root().parent(), None);
+        assert!(example.child(&[b'a'; 64]).is_err());
+        assert!(example.child(b"").is_err());
+
+        assert_eq!(name("www").join(&example), Ok(www.clone()));
+        assert_eq!(Name::root().join(&example), Ok(example.clone()));
+        assert!(www.is_subdomain_of(&name("EXAMPLE.com")));
+        assert!(www.is_subdomain_of(&www));
+        assert!(www.is_subdomain_of(&Name::root()));
+        assert!(!www.is_subdomain_of(&name("ample.com")));
+        assert!(!example.is_subdomain_of(&www));
+    }
+
+    #[test]
+    fn convert_wire() {
+        let www = name("www.example.com");
+        assert_eq!(Name::from_wire(www.as_wire()), Ok(www));
+        assert!(Name::from_wire(&[1, b'a']).is_err());
+        assert!(Name::from_wire(&[1, b'a', 0xC0, 0]).is_err());
+        assert_eq!(
+            Name::from_wire(&[0, 0]),
+            Err(ParseError::TrailingBytes { offset: 1 })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trip(name in strategies::name()) {
+            let parsed = name.to_string().parse::<Name>().unwrap();
+            prop_assert_eq!(parsed.as_wire(), name.as_wire());
+        }
+    }
+
+    #[test]
+    fn convert_unicode_labels() {
+        let bucher = name("Bücher.example");
+        assert_eq!(bucher.as_wire(), b"\x0dxn--bcher-kva\x07example\x00");
+        assert_eq!(bucher.to_string(), "xn--bcher-kva.example.");
+        assert_eq!(format!("{:#}", bucher), "bücher.example.");
+        assert_eq!(name("例え。テスト"), name("xn--r8jz45g.xn--zckzah"));
+        // Escaped bytes are taken as they are
+        assert_eq!(name("\\195\\188").as_wire(), b"\x02\xC3\xBC\x00");
+        // ASCII labels keep their case and stay as they are in both forms
+        assert_eq!(format!("{:#}", name("_Srv.xn--zz")), "_Srv.xn--zz.");
+    }
+
+    #[test]
+    fn reject_invalid_unicode_labels() {
+        assert_eq!(
+            "www.a\u{2488}b".parse::<Name>(),
+            Err(NameError::DisallowedCodePoint {
+                position: 4,
+                code_point: '\u{2488}'
+            })
+        );
+        assert_eq!(
+            "a b\u{e9}".parse::<Name>(),
+            Err(NameError::DisallowedCodePoint {
+                position: 0,
+                code_point: ' '
+            })
+        );
+        assert_eq!(
+            "-b\u{e9}".parse::<Name>(),
+            Err(NameError::InvalidIdna { position: 0 })
+        );
+        assert!(format!("{}.com", "\u{e9}".repeat(60))
+            .parse::<Name>()
+            .is_err());
+    }
+}
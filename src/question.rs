@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::encoder::Encoder;
+use crate::error::ParseError;
+use crate::name::Name;
+use crate::types::{Class, RecordType};
+use crate::utility;
+
+/// DNS question section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// Domain name
+    pub q_name: Name,
+    /// Type of query
+    pub q_type: RecordType,
+    /// Class of query
+    pub q_class: Class,
+}
+
+impl Question {
+    /// Transform to a vector of bytes
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(false);
+        self.encode(&mut encoder);
+        encoder.into_bytes()
+    }
+
+    /// Write the question into an encoder
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_name(&self.q_name);
+        encoder.put_u16(self.q_type.into());
+        encoder.put_u16(self.q_class.into());
+    }
+
+    /// Parse a vector of bytes to DNS question
+    pub fn parse(message: &[u8], start: usize) -> Result<(usize, Question), ParseError> {
+        let (start, q_name) = Name::parse(message, start)?;
+        let q_type = RecordType::from(utility::read_u16(message, start)?);
+        let q_class = Class::from(utility::read_u16(message, start + 2)?);
+
+        let q = Question {
+            q_name,
+            q_type,
+            q_class,
+        };
+
+        Ok((start + 4, q))
+    }
+}
+
+impl fmt::Display for Question {
+    /// Write the question as name, class and type separated by tabs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.q_name.fmt(f)?;
+        write!(f, "\t{}\t{}", self.q_class, self.q_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+
+    #[test]
+    fn create_question() {
+        let question = Question {
+            q_name: "hello".parse().unwrap(),
+            q_type: RecordType::A,
+            q_class: Class::IN,
+        };
+
+        let mut bytes = question.q_name.as_wire().to_vec();
+        bytes.push(0x00);
+        bytes.push(0x01);
+        bytes.push(0x00);
+        bytes.push(0x01);
+        assert_eq!(question.to_be_bytes(), bytes);
+    }
+
+    proptest! {
+        #[test]
+        fn question_round_trip(question in strategies::question()) {
+            let bytes = question.to_be_bytes();
+            prop_assert_eq!(Question::parse(&bytes, 0), Ok((bytes.len(), question)));
+        }
+    }
+}
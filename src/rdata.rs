@@ -0,0 +1,537 @@
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::encoder::Encoder;
+use crate::error::{EncodeError, ParseError};
+use crate::name::Name;
+use crate::types::RecordType;
+use crate::utility;
+
+/// Typed rdata of a resource record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    /// IPv4 host address
+    A(Ipv4Addr),
+    /// IPv6 host address
+    Aaaa(Ipv6Addr),
+    /// Authoritative name server
+    Ns(Name),
+    /// Canonical name for an alias
+    Cname(Name),
+    /// Domain name pointer
+    Ptr(Name),
+    /// Start of a zone of authority
+    Soa {
+        /// Name server that was the original source of data for this zone
+        mname: Name,
+        /// Mailbox of the person responsible for this zone
+        rname: Name,
+        /// Version number of the original copy of the zone
+        serial: u32,
+        /// Interval before the zone should be refreshed
+        refresh: u32,
+        /// Interval before a failed refresh should be retried
+        retry: u32,
+        /// Upper limit on the time before the zone is no longer authoritative
+        expire: u32,
+        /// TTL for negative caching
+        minimum: u32,
+    },
+    /// Mail exchange
+    Mx {
+        /// Preference given to this exchange, lower values are preferred
+        preference: u16,
+        /// Host acting as a mail exchange
+        exchange: Name,
+    },
+    /// Text strings
+    Txt(Vec<Vec<u8>>),
+    /// Location of a service (RFC 2782)
+    Srv {
+        /// Priority of the target host, lower values are preferred
+        priority: u16,
+        /// Relative weight for entries with the same priority
+        weight: u16,
+        /// Port of the service on the target host
+        port: u16,
+        /// Domain name of the target host
+        target: Name,
+    },
+    /// Certification authority authorization (RFC 8659)
+    Caa {
+        /// Flags, bit 7 is the issuer critical flag
+        flags: u8,
+        /// Property tag
+        tag: Vec<u8>,
+        /// Property value
+        value: Vec<u8>,
+    },
+    /// Host information
+    Hinfo {
+        /// CPU type
+        cpu: Vec<u8>,
+        /// Operating system
+        os: Vec<u8>,
+    },
+    /// Rdata of a type without a dedicated representation
+    Unknown(Vec<u8>),
+}
+
+impl RData {
+    /// Parse `rdlength` bytes of rdata of type `rr_type` starting at `start`
+    pub fn parse(
+        message: &[u8],
+        start: usize,
+        rdlength: usize,
+        rr_type: RecordType,
+    ) -> Result<RData, ParseError> {
+        let end = start + rdlength;
+        let invalid = ParseError::InvalidRData {
+            offset: start,
+            rr_type: rr_type.into(),
+        };
+        let rdata = message.get(start..end).ok_or(ParseError::RdLengthOverrun {
+            offset: start,
+            rdlength: rdlength as u16,
+        })?;
+        // Names may point anywhere before them but must not run past rdata
+        let message = &message[..end];
+
+        let (pos, rdata) = match rr_type {
+            RecordType::A => {
+                let octets: [u8; 4] = rdata.try_into().map_err(|_| invalid.clone())?;
+                (end, RData::A(Ipv4Addr::from(octets)))
+            }
+            RecordType::AAAA => {
+                let octets: [u8; 16] = rdata.try_into().map_err(|_| invalid.clone())?;
+                (end, RData::Aaaa(Ipv6Addr::from(octets)))
+            }
+            RecordType::NS => {
+                let (pos, ns) = Name::parse(message, start)?;
+                (pos, RData::Ns(ns))
+            }
+            RecordType::CNAME => {
+                let (pos, cname) = Name::parse(message, start)?;
+                (pos, RData::Cname(cname))
+            }
+            RecordType::PTR => {
+                let (pos, ptr) = Name::parse(message, start)?;
+                (pos, RData::Ptr(ptr))
+            }
+            RecordType::SOA => {
+                let (pos, mname) = Name::parse(message, start)?;
+                let (pos, rname) = Name::parse(message, pos)?;
+                let soa = RData::Soa {
+                    mname,
+                    rname,
+                    serial: utility::read_u32(message, pos)?,
+                    refresh: utility::read_u32(message, pos + 4)?,
+                    retry: utility::read_u32(message, pos + 8)?,
+                    expire: utility::read_u32(message, pos + 12)?,
+                    minimum: utility::read_u32(message, pos + 16)?,
+                };
+                (pos + 20, soa)
+            }
+            RecordType::MX => {
+                let preference = utility::read_u16(message, start)?;
+                let (pos, exchange) = Name::parse(message, start + 2)?;
+                let mx = RData::Mx {
+                    preference,
+                    exchange,
+                };
+                (pos, mx)
+            }
+            RecordType::TXT => {
+                let mut strings = vec![];
+                let mut pos = start;
+                while pos < end {
+                    let (next, string) = RData::parse_string(message, pos)?;
+                    strings.push(string);
+                    pos = next;
+                }
+                (pos, RData::Txt(strings))
+            }
+            RecordType::SRV => {
+                let priority = utility::read_u16(message, start)?;
+                let weight = utility::read_u16(message, start + 2)?;
+                let port = utility::read_u16(message, start + 4)?;
+                let (pos, target) = Name::parse(message, start + 6)?;
+                let srv = RData::Srv {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                };
+                (pos, srv)
+            }
+            RecordType::CAA => {
+                let flags = *rdata.first().ok_or(invalid.clone())?;
+                let (pos, tag) = RData::parse_string(message, start + 1)?;
+                let caa = RData::Caa {
+                    flags,
+                    tag,
+                    value: message[pos..end].to_vec(),
+                };
+                (end, caa)
+            }
+            RecordType::HINFO => {
+                let (pos, cpu) = RData::parse_string(message, start)?;
+                let (pos, os) = RData::parse_string(message, pos)?;
+                (pos, RData::Hinfo { cpu, os })
+            }
+            // MD, MF, MB, MG, MR and MINFO have no dedicated representation
+            // but may still compress their names, which are kept expanded
+            RecordType::Unknown(code @ (3 | 4 | 7 | 8 | 9 | 14)) => {
+                let names = if code == 14 { 2 } else { 1 };
+                let mut expanded = vec![];
+                let mut pos = start;
+                for _ in 0..names {
+                    let (next, name) = Name::parse(message, pos)?;
+                    expanded.extend_from_slice(name.as_wire());
+                    pos = next;
+                }
+                (pos, RData::Unknown(expanded))
+            }
+            _ => (end, RData::Unknown(rdata.to_vec())),
+        };
+
+        if pos != end {
+            return Err(invalid);
+        }
+
+        Ok(rdata)
+    }
+
+    /// Type of record the rdata is the dedicated representation of, `None`
+    /// for rdata kept as bytes, which any type may carry (RFC 3597)
+    pub fn record_type(&self) -> Option<RecordType> {
+        match self {
+            RData::A(_) => Some(RecordType::A),
+            RData::Aaaa(_) => Some(RecordType::AAAA),
+            RData::Ns(_) => Some(RecordType::NS),
+            RData::Cname(_) => Some(RecordType::CNAME),
+            RData::Ptr(_) => Some(RecordType::PTR),
+            RData::Soa { .. } => Some(RecordType::SOA),
+            RData::Mx { .. } => Some(RecordType::MX),
+            RData::Txt(_) => Some(RecordType::TXT),
+            RData::Srv { .. } => Some(RecordType::SRV),
+            RData::Caa { .. } => Some(RecordType::CAA),
+            RData::Hinfo { .. } => Some(RecordType::HINFO),
+            RData::Unknown(_) => None,
+        }
+    }
+
+    /// Parse a character string, a length octet followed by that many bytes
+    fn parse_string(message: &[u8], start: usize) -> Result<(usize, Vec<u8>), ParseError> {
+        let len = *message
+            .get(start)
+            .ok_or(ParseError::Truncated { offset: start })? as usize;
+        let string = message
+            .get(start + 1..start + 1 + len)
+            .ok_or(ParseError::Truncated { offset: start })?;
+        Ok((start + 1 + len, string.to_vec()))
+    }
+
+    /// Transform rdata to a vector of bytes
+    pub fn to_be_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut encoder = Encoder::new(false);
+        self.encode(&mut encoder)?;
+        Ok(encoder.into_bytes())
+    }
+
+    /// Write rdata into an encoder. Only the names of the types defined in
+    /// RFC 1035 are compressed (RFC 3597)
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<(), EncodeError> {
+        match self {
+            RData::A(addr) => encoder.put_bytes(&addr.octets()),
+            RData::Aaaa(addr) => encoder.put_bytes(&addr.octets()),
+            RData::Ns(name) | RData::Cname(name) | RData::Ptr(name) => encoder.put_name(name),
+            RData::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => {
+                encoder.put_name(mname);
+                encoder.put_name(rname);
+                encoder.put_u32(*serial);
+                encoder.put_u32(*refresh);
+                encoder.put_u32(*retry);
+                encoder.put_u32(*expire);
+                encoder.put_u32(*minimum);
+            }
+            RData::Mx {
+                preference,
+                exchange,
+            } => {
+                encoder.put_u16(*preference);
+                encoder.put_name(exchange);
+            }
+            RData::Txt(strings) => {
+                for string in strings {
+                    RData::encode_string(string, encoder)?;
+                }
+            }
+            RData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                encoder.put_u16(*priority);
+                encoder.put_u16(*weight);
+                encoder.put_u16(*port);
+                encoder.put_bytes(target.as_wire());
+            }
+            RData::Caa { flags, tag, value } => {
+                encoder.put_u8(*flags);
+                RData::encode_string(tag, encoder)?;
+                encoder.put_bytes(value);
+            }
+            RData::Hinfo { cpu, os } => {
+                RData::encode_string(cpu, encoder)?;
+                RData::encode_string(os, encoder)?;
+            }
+            RData::Unknown(data) => encoder.put_bytes(data),
+        }
+        Ok(())
+    }
+
+    /// Write a character string, which must not exceed 255 bytes
+    fn encode_string(string: &[u8], encoder: &mut Encoder) -> Result<(), EncodeError> {
+        let len = u8::try_from(string.len()).map_err(|_| EncodeError::StringTooLong {
+            length: string.len(),
+        })?;
+        encoder.put_u8(len);
+        encoder.put_bytes(string);
+        Ok(())
+    }
+}
+
+impl fmt::Display for RData {
+    /// Write rdata in master file format. Rdata without a dedicated
+    /// representation uses the generic `\# length hex` form (RFC 3597). The
+    /// alternate form `{:#}` shows names with U-labels
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{}", addr),
+            RData::Aaaa(addr) => write!(f, "{}", addr),
+            RData::Ns(name) | RData::Cname(name) | RData::Ptr(name) => name.fmt(f),
+            RData::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => {
+                mname.fmt(f)?;
+                write!(f, " ")?;
+                rname.fmt(f)?;
+                write!(
+                    f,
+                    " {} {} {} {} {}",
+                    serial, refresh, retry, expire, minimum
+                )
+            }
+            RData::Mx {
+                preference,
+                exchange,
+            } => {
+                write!(f, "{} ", preference)?;
+                exchange.fmt(f)
+            }
+            RData::Txt(strings) => {
+                for (i, string) in strings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write_string(f, string)?;
+                }
+                Ok(())
+            }
+            RData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                write!(f, "{} {} {} ", priority, weight, port)?;
+                target.fmt(f)
+            }
+            RData::Caa { flags, tag, value } => {
+                write!(f, "{} {} ", flags, String::from_utf8_lossy(tag))?;
+                write_string(f, value)
+            }
+            RData::Hinfo { cpu, os } => {
+                write_string(f, cpu)?;
+                write!(f, " ")?;
+                write_string(f, os)
+            }
+            RData::Unknown(data) if data.is_empty() => write!(f, "\\# 0"),
+            RData::Unknown(data) => write!(f, "\\# {} {}", data.len(), utility::hex(data)),
+        }
+    }
+}
+
+/// Write a character string between quotes, escaping quotes, backslashes
+/// and bytes that are not printable
+fn write_string(f: &mut fmt::Formatter<'_>, string: &[u8]) -> fmt::Result {
+    write!(f, "\"")?;
+    for &byte in string {
+        match byte {
+            b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+            0x20..=0x7E => write!(f, "{}", byte as char)?,
+            _ => write!(f, "\\{:03}", byte)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(rdata: RData, rr_type: u16) {
+        let rr_type = RecordType::from(rr_type);
+        let bytes = rdata.to_be_bytes().unwrap();
+        let parsed = RData::parse(&bytes, 0, bytes.len(), rr_type).unwrap();
+        assert_eq!(parsed, rdata);
+    }
+
+    #[test]
+    fn round_trip_every_type() {
+        let example_com: Name = "example.com".parse().unwrap();
+        round_trip(RData::A(Ipv4Addr::new(8, 8, 8, 8)), 1);
+        round_trip(RData::Aaaa("2001:db8::1".parse().unwrap()), 28);
+        round_trip(RData::Ns(example_com.clone()), 2);
+        round_trip(RData::Cname(example_com.clone()), 5);
+        round_trip(RData::Ptr(example_com.clone()), 12);
+        round_trip(
+            RData::Soa {
+                mname: example_com.clone(),
+                rname: example_com.clone(),
+                serial: 2024010101,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 300,
+            },
+            6,
+        );
+        round_trip(
+            RData::Mx {
+                preference: 10,
+                exchange: example_com.clone(),
+            },
+            15,
+        );
+        round_trip(RData::Txt(vec![b"v=spf1".to_vec(), vec![]]), 16);
+        round_trip(
+            RData::Srv {
+                priority: 1,
+                weight: 2,
+                port: 53,
+                target: example_com,
+            },
+            33,
+        );
+        round_trip(
+            RData::Caa {
+                flags: 128,
+                tag: b"issue".to_vec(),
+                value: b"letsencrypt.org".to_vec(),
+            },
+            257,
+        );
+        round_trip(
+            RData::Hinfo {
+                cpu: b"x86".to_vec(),
+                os: b"Linux".to_vec(),
+            },
+            13,
+        );
+        round_trip(RData::Unknown(vec![1, 2, 3]), 99);
+    }
+
+    #[test]
+    fn reject_string_longer_than_255_bytes() {
+        let long = vec![b'a'; 300];
+        let err = EncodeError::StringTooLong { length: 300 };
+        assert_eq!(
+            RData::Txt(vec![long.clone()]).to_be_bytes(),
+            Err(err.clone())
+        );
+        let hinfo = RData::Hinfo {
+            cpu: long.clone(),
+            os: b"Linux".to_vec(),
+        };
+        assert_eq!(hinfo.to_be_bytes(), Err(err.clone()));
+        let caa = RData::Caa {
+            flags: 0,
+            tag: long,
+            value: vec![],
+        };
+        assert_eq!(caa.to_be_bytes(), Err(err));
+        assert!(RData::Txt(vec![vec![b'a'; 255]]).to_be_bytes().is_ok());
+    }
+
+    #[test]
+    fn reject_wrong_address_length() {
+        assert_eq!(
+            RData::parse(&[1, 2, 3], 0, 3, RecordType::A),
+            Err(ParseError::InvalidRData {
+                offset: 0,
+                rr_type: 1
+            })
+        );
+        assert!(RData::parse(&[0; 4], 0, 4, RecordType::AAAA).is_err());
+    }
+
+    #[test]
+    fn reject_rdlength_longer_than_rdata() {
+        let bytes = [1, b'a', 0, 0xFF];
+        assert!(RData::parse(&bytes, 0, 4, RecordType::NS).is_err());
+    }
+
+    #[test]
+    fn display_presentation_format() {
+        let example_com: Name = "example.com".parse().unwrap();
+        let soa = RData::Soa {
+            mname: example_com.clone(),
+            rname: "hostmaster.example.com".parse().unwrap(),
+            serial: 2024010101,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        };
+        assert_eq!(
+            soa.to_string(),
+            "example.com. hostmaster.example.com. 2024010101 7200 3600 1209600 300"
+        );
+        let mx = RData::Mx {
+            preference: 10,
+            exchange: "mail.bücher.de".parse().unwrap(),
+        };
+        assert_eq!(mx.to_string(), "10 mail.xn--bcher-kva.de.");
+        assert_eq!(format!("{:#}", mx), "10 mail.bücher.de.");
+        let txt = RData::Txt(vec![b"say \"hi\"\\".to_vec(), vec![0xFF, b' ']]);
+        assert_eq!(txt.to_string(), r#""say \"hi\"\\" "\255 ""#);
+        let caa = RData::Caa {
+            flags: 128,
+            tag: b"issue".to_vec(),
+            value: b"letsencrypt.org".to_vec(),
+        };
+        assert_eq!(caa.to_string(), "128 issue \"letsencrypt.org\"");
+        assert_eq!(
+            RData::Unknown(vec![0x0A, 0, 0, 1]).to_string(),
+            r"\# 4 0a000001"
+        );
+        assert_eq!(RData::Unknown(vec![]).to_string(), r"\# 0");
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+
+use crate::encoder::Encoder;
+use crate::error::{EncodeError, ParseError};
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::types::{Class, RecordType};
+use crate::utility;
+
+/// DNS resource record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRecord {
+    /// A domain name to which this resource record pertains
+    pub an_name: Name,
+    /// RR type codes specifying the meaning in rdata
+    pub an_type: RecordType,
+    /// Class of the data in rdata
+    pub an_class: Class,
+    /// Time interval in seconds
+    pub an_ttl: u32,
+    /// The resource data. The format varies according to the type and class
+    /// of the resource record
+    pub an_rdata: RData,
+}
+
+impl ResourceRecord {
+    /// Transform a resource record to a vector of bytes
+    pub fn to_be_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut encoder = Encoder::new(false);
+        self.encode(&mut encoder)?;
+        Ok(encoder.into_bytes())
+    }
+
+    /// Write the resource record into an encoder, whose rdata must match
+    /// its type
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<(), EncodeError> {
+        if let Some(rdata_type) = self.an_rdata.record_type() {
+            if rdata_type != self.an_type {
+                return Err(EncodeError::RDataMismatch {
+                    rr_type: self.an_type.into(),
+                    rdata_type: rdata_type.into(),
+                });
+            }
+        }
+        encoder.put_name(&self.an_name);
+        encoder.put_u16(self.an_type.into());
+        encoder.put_u16(self.an_class.into());
+        encoder.put_u32(self.an_ttl);
+
+        let rdlength_pos = encoder.position();
+        encoder.put_u16(0);
+        self.an_rdata.encode(encoder)?;
+        let length = encoder.position() - rdlength_pos - 2;
+        let rdlength = u16::try_from(length).map_err(|_| EncodeError::RDataTooLong {
+            rr_type: self.an_type.into(),
+            length,
+        })?;
+        encoder.set_u16(rdlength_pos, rdlength);
+        Ok(())
+    }
+
+    /// Parse a vector of bytes into a resource record
+    pub fn parse(message: &[u8], start: usize) -> Result<(usize, ResourceRecord), ParseError> {
+        let (offset, an_name) = Name::parse(message, start)?;
+        let an_type = RecordType::from(utility::read_u16(message, offset)?);
+        let an_class = Class::from(utility::read_u16(message, offset + 2)?);
+        let an_ttl = utility::read_u32(message, offset + 4)?;
+        let rdlength = utility::read_u16(message, offset + 8)?;
+        if offset + 10 + rdlength as usize > message.len() {
+            return Err(ParseError::RdLengthOverrun {
+                offset: offset + 8,
+                rdlength,
+            });
+        }
+        let rdlength = rdlength as usize;
+        let an_rdata = RData::parse(message, offset + 10, rdlength, an_type)?;
+
+        let rr = ResourceRecord {
+            an_name,
+            an_type,
+            an_class,
+            an_ttl,
+            an_rdata,
+        };
+        Ok((offset + 10 + rdlength, rr))
+    }
+}
+
+impl fmt::Display for ResourceRecord {
+    /// Write the record as a line of a master file: owner, TTL, class, type
+    /// and rdata separated by tabs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.an_name.fmt(f)?;
+        write!(
+            f,
+            "\t{}\t{}\t{}\t",
+            self.an_ttl, self.an_class, self.an_type
+        )?;
+        self.an_rdata.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+    use std::net::Ipv4Addr;
+
+    const EXAMPLE_COM: [u8; 13] = [
+        7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+    ];
+
+    fn example_com() -> Name {
+        Name::from_wire(&EXAMPLE_COM).unwrap()
+    }
+
+    #[test]
+    fn parse_compressed_owner_and_rdata() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&EXAMPLE_COM);
+        // www.example.com CNAME mail.example.com
+        message.extend_from_slice(&[3, b'w', b'w', b'w', 0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 7]);
+        message.extend_from_slice(&[4, b'm', b'a', b'i', b'l', 0xC0, 12]);
+
+        let (end, rr) = ResourceRecord::parse(&message, 25).unwrap();
+        assert_eq!(end, message.len());
+        assert_eq!(rr.an_name.to_string(), "www.example.com.");
+        assert_eq!(rr.an_type, RecordType::CNAME);
+        assert_eq!(rr.an_ttl, 60);
+        match rr.an_rdata {
+            RData::Cname(cname) => assert_eq!(cname.to_string(), "mail.example.com."),
+            rdata => panic!("Unexpected rdata {:?}", rdata),
+        }
+    }
+
+    #[test]
+    fn parse_compressed_mx_rdata() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&[3, b'c', b'o', b'm', 0]);
+        // com MX 10 mx.com
+        message.extend_from_slice(&[0xC0, 12, 0, 15, 0, 1, 0, 0, 0, 60, 0, 7]);
+        message.extend_from_slice(&[0, 10, 2, b'm', b'x', 0xC0, 12]);
+
+        let (_, rr) = ResourceRecord::parse(&message, 17).unwrap();
+        let exchange = "mx.com".parse().unwrap();
+        assert_eq!(
+            rr.an_rdata,
+            RData::Mx {
+                preference: 10,
+                exchange
+            }
+        );
+    }
+
+    #[test]
+    fn expand_compressed_minfo_rdata() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&EXAMPLE_COM);
+        // example.com MINFO admin.example.com errors.example.com
+        message.extend_from_slice(&[0xC0, 12, 0, 14, 0, 1, 0, 0, 0, 60, 0, 17]);
+        message.extend_from_slice(&[5, b'a', b'd', b'm', b'i', b'n', 0xC0, 12]);
+        message.extend_from_slice(&[6, b'e', b'r', b'r', b'o', b'r', b's', 0xC0, 12]);
+
+        let (end, rr) = ResourceRecord::parse(&message, 25).unwrap();
+        assert_eq!(end, message.len());
+        let admin: Name = "admin.example.com".parse().unwrap();
+        let errors: Name = "errors.example.com".parse().unwrap();
+        let rdata = [admin.as_wire(), errors.as_wire()].concat();
+        assert_eq!(rr.an_rdata, RData::Unknown(rdata));
+
+        // Re-encoded at another offset, the names no longer rely on pointers
+        let mut encoder = Encoder::new(true);
+        encoder.put_bytes(&[0; 20]);
+        rr.encode(&mut encoder).unwrap();
+        let bytes = encoder.into_bytes();
+        assert_eq!(ResourceRecord::parse(&bytes, 20), Ok((bytes.len(), rr)));
+    }
+
+    #[test]
+    fn compress_rdata_names() {
+        let rr = ResourceRecord {
+            an_name: example_com(),
+            an_type: RecordType::MX,
+            an_class: Class::IN,
+            an_ttl: 60,
+            an_rdata: RData::Mx {
+                preference: 10,
+                exchange: example_com(),
+            },
+        };
+
+        let mut encoder = Encoder::new(true);
+        encoder.put_bytes(&[0; 12]);
+        rr.encode(&mut encoder).unwrap();
+        let bytes = encoder.into_bytes();
+        assert_eq!(bytes[33..], [0, 4, 0, 10, 0xC0, 12]);
+
+        let (_, parsed) = ResourceRecord::parse(&bytes, 12).unwrap();
+        assert_eq!(parsed, rr);
+    }
+
+    #[test]
+    fn keep_srv_target_uncompressed() {
+        let rr = ResourceRecord {
+            an_name: example_com(),
+            an_type: RecordType::SRV,
+            an_class: Class::IN,
+            an_ttl: 60,
+            an_rdata: RData::Srv {
+                priority: 1,
+                weight: 2,
+                port: 53,
+                target: example_com(),
+            },
+        };
+
+        let mut encoder = Encoder::new(true);
+        rr.encode(&mut encoder).unwrap();
+        assert!(encoder.into_bytes().ends_with(&EXAMPLE_COM));
+    }
+
+    #[test]
+    fn reject_rdata_name_beyond_rdlength() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&[3, b'c', b'o', b'm', 0]);
+        // NS record whose rdlength cuts the name short
+        message.extend_from_slice(&[0xC0, 12, 0, 2, 0, 1, 0, 0, 0, 60, 0, 2]);
+        message.extend_from_slice(&[2, b'n', b's', 0xC0, 12]);
+
+        assert_eq!(
+            ResourceRecord::parse(&message, 17),
+            Err(ParseError::Truncated { offset: 29 })
+        );
+    }
+
+    #[test]
+    fn reject_rdlength_overrun() {
+        let mut message = vec![0; 12];
+        message.extend_from_slice(&[3, b'c', b'o', b'm', 0]);
+        message.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 60, 0, 5, 1, 2, 3, 4]);
+
+        assert_eq!(
+            ResourceRecord::parse(&message, 12),
+            Err(ParseError::RdLengthOverrun {
+                offset: 25,
+                rdlength: 5
+            })
+        );
+    }
+
+    #[test]
+    fn reject_rdata_of_another_type() {
+        let mut rr = ResourceRecord {
+            an_name: example_com(),
+            an_type: RecordType::MX,
+            an_class: Class::IN,
+            an_ttl: 60,
+            an_rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        };
+        assert_eq!(
+            rr.to_be_bytes(),
+            Err(EncodeError::RDataMismatch {
+                rr_type: 15,
+                rdata_type: 1
+            })
+        );
+
+        rr.an_rdata = RData::Unknown(vec![0, 10, 0]);
+        assert!(rr.to_be_bytes().is_ok());
+    }
+
+    #[test]
+    fn reject_rdata_longer_than_rdlength_allows() {
+        let mut rr = ResourceRecord {
+            an_name: example_com(),
+            an_type: RecordType::TXT,
+            an_class: Class::IN,
+            an_ttl: 60,
+            an_rdata: RData::Txt(vec![vec![b'a'; 255]; 257]),
+        };
+        assert_eq!(
+            rr.to_be_bytes(),
+            Err(EncodeError::RDataTooLong {
+                rr_type: 16,
+                length: 65792
+            })
+        );
+
+        rr.an_rdata = RData::Txt(vec![vec![b'a'; 255]; 255]);
+        assert!(rr.to_be_bytes().is_ok());
+    }
+
+    #[test]
+    fn display_master_file_line() {
+        let rr = ResourceRecord {
+            an_name: example_com(),
+            an_type: RecordType::Unknown(65280),
+            an_class: Class::Unknown(32),
+            an_ttl: 60,
+            an_rdata: RData::Unknown(vec![0xAB, 0xCD]),
+        };
+        assert_eq!(
+            rr.to_string(),
+            "example.com.\t60\tCLASS32\tTYPE65280\t\\# 2 abcd"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn resource_record_round_trip(rr in strategies::resource_record()) {
+            let bytes = rr.to_be_bytes().unwrap();
+            prop_assert_eq!(ResourceRecord::parse(&bytes, 0), Ok((bytes.len(), rr)));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Proptest strategies generating valid DNS message parts
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::edns::{Edns, EdnsOption};
+use crate::header::{Flag, Header};
+use crate::message::DnsMessage;
+use crate::name::Name;
+use crate::question::Question;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::{Class, RecordType};
+
+/// Domain name of up to four labels of arbitrary bytes
+pub fn name() -> impl Strategy<Value = Name> {
+    vec(vec(any::<u8>(), 1..=20), 0..=4).prop_map(|labels| {
+        let mut name = vec![];
+        for label in labels {
+            name.push(label.len() as u8);
+            name.extend(label);
+        }
+        name.push(0);
+        Name::from_wire(&name).unwrap()
+    })
+}
+
+/// Character string of up to 255 bytes
+fn character_string() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..=255)
+}
+
+pub fn flag() -> impl Strategy<Value = Flag> {
+    any::<u16>().prop_map(Flag::parse)
+}
+
+pub fn header() -> impl Strategy<Value = Header> {
+    (
+        any::<u16>(),
+        flag(),
+        any::<u16>(),
+        any::<u16>(),
+        any::<u16>(),
+        any::<u16>(),
+    )
+        .prop_map(|(id, flags, qd_cnt, an_cnt, ns_cnt, ar_cnt)| Header {
+            id,
+            flags,
+            qd_cnt,
+            an_cnt,
+            ns_cnt,
+            ar_cnt,
+        })
+}
+
+pub fn question() -> impl Strategy<Value = Question> {
+    (name(), any::<u16>(), any::<u16>()).prop_map(|(q_name, q_type, q_class)| Question {
+        q_name,
+        q_type: RecordType::from(q_type),
+        q_class: Class::from(q_class),
+    })
+}
+
+/// Rdata of every supported type together with its type code
+pub fn rdata() -> impl Strategy<Value = (u16, RData)> {
+    prop_oneof![
+        any::<[u8; 4]>().prop_map(|octets| (1, RData::A(Ipv4Addr::from(octets)))),
+        any::<[u8; 16]>().prop_map(|octets| (28, RData::Aaaa(Ipv6Addr::from(octets)))),
+        name().prop_map(|name| (2, RData::Ns(name))),
+        name().prop_map(|name| (5, RData::Cname(name))),
+        name().prop_map(|name| (12, RData::Ptr(name))),
+        (name(), name(), any::<[u32; 5]>()).prop_map(|(mname, rname, times)| {
+            let soa = RData::Soa {
+                mname,
+                rname,
+                serial: times[0],
+                refresh: times[1],
+                retry: times[2],
+                expire: times[3],
+                minimum: times[4],
+            };
+            (6, soa)
+        }),
+        (any::<u16>(), name()).prop_map(|(preference, exchange)| {
+            (
+                15,
+                RData::Mx {
+                    preference,
+                    exchange,
+                },
+            )
+        }),
+        vec(character_string(), 0..4).prop_map(|strings| (16, RData::Txt(strings))),
+        (any::<[u16; 3]>(), name()).prop_map(|(fields, target)| {
+            let srv = RData::Srv {
+                priority: fields[0],
+                weight: fields[1],
+                port: fields[2],
+                target,
+            };
+            (33, srv)
+        }),
+        (any::<u8>(), "[a-z0-9]{1,15}", vec(any::<u8>(), 0..64)).prop_map(|(flags, tag, value)| {
+            let tag = tag.into_bytes();
+            (257, RData::Caa { flags, tag, value })
+        }),
+        (character_string(), character_string())
+            .prop_map(|(cpu, os)| (13, RData::Hinfo { cpu, os })),
+        (
+            any::<u16>().prop_filter("type with names or a typed rdata", |rr_type| {
+                !matches!(rr_type, 1..=9 | 12..=16 | 28 | 33 | 257)
+            }),
+            vec(any::<u8>(), 0..64)
+        )
+            .prop_map(|(rr_type, data)| (rr_type, RData::Unknown(data))),
+    ]
+}
+
+pub fn resource_record() -> impl Strategy<Value = ResourceRecord> {
+    (name(), rdata(), any::<u16>(), any::<u32>()).prop_map(
+        |(an_name, (an_type, an_rdata), an_class, an_ttl)| ResourceRecord {
+            an_name,
+            an_type: RecordType::from(an_type),
+            an_class: Class::from(an_class),
+            an_ttl,
+            an_rdata,
+        },
+    )
+}
+
+pub fn edns_option() -> impl Strategy<Value = EdnsOption> {
+    prop_oneof![
+        vec(any::<u8>(), 0..32).prop_map(EdnsOption::Nsid),
+        (any::<[u8; 4]>(), 0..=32u8, any::<u8>()).prop_map(|(octets, prefix, scope)| {
+            client_subnet(IpAddr::V4(Ipv4Addr::from(octets)), prefix, scope)
+        }),
+        (any::<[u8; 16]>(), 0..=128u8, any::<u8>()).prop_map(|(octets, prefix, scope)| {
+            client_subnet(IpAddr::V6(Ipv6Addr::from(octets)), prefix, scope)
+        }),
+        (
+            any::<[u8; 8]>(),
+            proptest::option::of(vec(any::<u8>(), 8..=32))
+        )
+            .prop_map(|(client, server)| EdnsOption::Cookie { client, server }),
+        (0..512u16).prop_map(EdnsOption::Padding),
+        (any::<u16>(), ".{0,32}").prop_map(|(info_code, extra_text)| {
+            EdnsOption::ExtendedError {
+                info_code,
+                extra_text,
+            }
+        }),
+        (
+            any::<u16>().prop_filter("code with a typed option", |code| {
+                !matches!(*code, 3 | 8 | 10 | 12 | 15)
+            }),
+            vec(any::<u8>(), 0..32)
+        )
+            .prop_map(|(code, data)| EdnsOption::Unknown { code, data }),
+    ]
+}
+
+fn client_subnet(address: IpAddr, source_prefix: u8, scope_prefix: u8) -> EdnsOption {
+    match EdnsOption::client_subnet(address, source_prefix) {
+        EdnsOption::ClientSubnet { address, .. } => EdnsOption::ClientSubnet {
+            source_prefix,
+            scope_prefix,
+            address,
+        },
+        option => option,
+    }
+}
+
+pub fn edns() -> impl Strategy<Value = Edns> {
+    (
+        any::<u16>(),
+        any::<u8>(),
+        any::<u8>(),
+        any::<bool>(),
+        0..0x8000u16,
+        vec(edns_option(), 0..4),
+    )
+        .prop_map(
+            |(udp_payload_size, extended_rcode, version, dnssec_ok, z, options)| Edns {
+                udp_payload_size,
+                extended_rcode,
+                version,
+                dnssec_ok,
+                z,
+                options,
+            },
+        )
+}
+
+/// DNS message whose header counts match its sections
+pub fn message() -> impl Strategy<Value = DnsMessage> {
+    // OPT only stands for EDNS, lifted out of the additional section
+    let record =
+        || resource_record().prop_filter("OPT is not a record", |rr| rr.an_type != RecordType::OPT);
+    (
+        header(),
+        vec(question(), 0..3),
+        vec(record(), 0..4),
+        vec(record(), 0..4),
+        vec(record(), 0..4),
+        proptest::option::of(edns()),
+    )
+        .prop_map(
+            |(mut header, questions, answers, authorities, additionals, edns)| {
+                header.qd_cnt = questions.len() as u16;
+                header.an_cnt = answers.len() as u16;
+                header.ns_cnt = authorities.len() as u16;
+                header.ar_cnt = (additionals.len() + edns.is_some() as usize) as u16;
+                DnsMessage {
+                    header,
+                    questions,
+                    answers,
+                    authorities,
+                    additionals,
+                    edns,
+                }
+            },
+        )
+}
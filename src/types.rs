@@ -0,0 +1,292 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Type of a resource record, or of the records asked for in a question
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    /// IPv4 host address
+    A,
+    /// Authoritative name server
+    NS,
+    /// Canonical name for an alias
+    CNAME,
+    /// Start of a zone of authority
+    SOA,
+    /// Domain name pointer
+    PTR,
+    /// Host information
+    HINFO,
+    /// Mail exchange
+    MX,
+    /// Text strings
+    TXT,
+    /// IPv6 host address (RFC 3596)
+    AAAA,
+    /// Location of a service (RFC 2782)
+    SRV,
+    /// EDNS pseudo-record (RFC 6891)
+    OPT,
+    /// Delegation signer (RFC 4034)
+    DS,
+    /// Resource record signature (RFC 4034)
+    RRSIG,
+    /// Next secure record (RFC 4034)
+    NSEC,
+    /// DNS public key (RFC 4034)
+    DNSKEY,
+    /// Incremental zone transfer (RFC 1995), question only
+    IXFR,
+    /// Full zone transfer, question only
+    AXFR,
+    /// All records, question only
+    ANY,
+    /// Certification authority authorization (RFC 8659)
+    CAA,
+    /// Type without a dedicated variant
+    Unknown(u16),
+}
+
+impl From<u16> for RecordType {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => RecordType::A,
+            2 => RecordType::NS,
+            5 => RecordType::CNAME,
+            6 => RecordType::SOA,
+            12 => RecordType::PTR,
+            13 => RecordType::HINFO,
+            15 => RecordType::MX,
+            16 => RecordType::TXT,
+            28 => RecordType::AAAA,
+            33 => RecordType::SRV,
+            41 => RecordType::OPT,
+            43 => RecordType::DS,
+            46 => RecordType::RRSIG,
+            47 => RecordType::NSEC,
+            48 => RecordType::DNSKEY,
+            251 => RecordType::IXFR,
+            252 => RecordType::AXFR,
+            255 => RecordType::ANY,
+            257 => RecordType::CAA,
+            _ => RecordType::Unknown(value),
+        }
+    }
+}
+
+impl From<RecordType> for u16 {
+    fn from(value: RecordType) -> Self {
+        match value {
+            RecordType::A => 1,
+            RecordType::NS => 2,
+            RecordType::CNAME => 5,
+            RecordType::SOA => 6,
+            RecordType::PTR => 12,
+            RecordType::HINFO => 13,
+            RecordType::MX => 15,
+            RecordType::TXT => 16,
+            RecordType::AAAA => 28,
+            RecordType::SRV => 33,
+            RecordType::OPT => 41,
+            RecordType::DS => 43,
+            RecordType::RRSIG => 46,
+            RecordType::NSEC => 47,
+            RecordType::DNSKEY => 48,
+            RecordType::IXFR => 251,
+            RecordType::AXFR => 252,
+            RecordType::ANY => 255,
+            RecordType::CAA => 257,
+            RecordType::Unknown(value) => value,
+        }
+    }
+}
+
+impl RecordType {
+    /// Every type with a dedicated variant
+    const KNOWN: [RecordType; 19] = [
+        RecordType::A,
+        RecordType::NS,
+        RecordType::CNAME,
+        RecordType::SOA,
+        RecordType::PTR,
+        RecordType::HINFO,
+        RecordType::MX,
+        RecordType::TXT,
+        RecordType::AAAA,
+        RecordType::SRV,
+        RecordType::OPT,
+        RecordType::DS,
+        RecordType::RRSIG,
+        RecordType::NSEC,
+        RecordType::DNSKEY,
+        RecordType::IXFR,
+        RecordType::AXFR,
+        RecordType::ANY,
+        RecordType::CAA,
+    ];
+
+    /// Mnemonic of the type, if it has one
+    fn mnemonic(&self) -> Option<&'static str> {
+        let mnemonic = match self {
+            RecordType::A => "A",
+            RecordType::NS => "NS",
+            RecordType::CNAME => "CNAME",
+            RecordType::SOA => "SOA",
+            RecordType::PTR => "PTR",
+            RecordType::HINFO => "HINFO",
+            RecordType::MX => "MX",
+            RecordType::TXT => "TXT",
+            RecordType::AAAA => "AAAA",
+            RecordType::SRV => "SRV",
+            RecordType::OPT => "OPT",
+            RecordType::DS => "DS",
+            RecordType::RRSIG => "RRSIG",
+            RecordType::NSEC => "NSEC",
+            RecordType::DNSKEY => "DNSKEY",
+            RecordType::IXFR => "IXFR",
+            RecordType::AXFR => "AXFR",
+            RecordType::ANY => "ANY",
+            RecordType::CAA => "CAA",
+            RecordType::Unknown(_) => return None,
+        };
+        Some(mnemonic)
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mnemonic() {
+            Some(mnemonic) => write!(f, "{}", mnemonic),
+            None => write!(f, "TYPE{}", u16::from(*self)),
+        }
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = String;
+
+    /// Parse a type mnemonic, or the generic `TYPEnnn` form of RFC 3597
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        if let Some(value) = upper.strip_prefix("TYPE") {
+            if let Ok(value) = value.parse::<u16>() {
+                return Ok(RecordType::from(value));
+            }
+        }
+        RecordType::KNOWN
+            .into_iter()
+            .find(|rr_type| rr_type.mnemonic() == Some(upper.as_str()))
+            .ok_or_else(|| format!("Unknown record type: {}", s))
+    }
+}
+
+/// Class of a resource record or question
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Class {
+    /// The Internet
+    IN,
+    /// Chaos
+    CH,
+    /// Hesiod
+    HS,
+    /// No class, used by dynamic updates (RFC 2136)
+    NONE,
+    /// Any class, question only
+    ANY,
+    /// Class without a dedicated variant
+    Unknown(u16),
+}
+
+impl From<u16> for Class {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => Class::IN,
+            3 => Class::CH,
+            4 => Class::HS,
+            254 => Class::NONE,
+            255 => Class::ANY,
+            _ => Class::Unknown(value),
+        }
+    }
+}
+
+impl From<Class> for u16 {
+    fn from(value: Class) -> Self {
+        match value {
+            Class::IN => 1,
+            Class::CH => 3,
+            Class::HS => 4,
+            Class::NONE => 254,
+            Class::ANY => 255,
+            Class::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Class::IN => write!(f, "IN"),
+            Class::CH => write!(f, "CH"),
+            Class::HS => write!(f, "HS"),
+            Class::NONE => write!(f, "NONE"),
+            Class::ANY => write!(f, "ANY"),
+            Class::Unknown(value) => write!(f, "CLASS{}", value),
+        }
+    }
+}
+
+impl FromStr for Class {
+    type Err = String;
+
+    /// Parse a class mnemonic, or the generic `CLASSnnn` form of RFC 3597
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "IN" => Ok(Class::IN),
+            "CH" | "CHAOS" => Ok(Class::CH),
+            "HS" | "HESIOD" => Ok(Class::HS),
+            "NONE" => Ok(Class::NONE),
+            "ANY" => Ok(Class::ANY),
+            upper => upper
+                .strip_prefix("CLASS")
+                .and_then(|value| value.parse::<u16>().ok())
+                .map(Class::from)
+                .ok_or_else(|| format!("Unknown class: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_codes() {
+        for value in 0..=u16::MAX {
+            assert_eq!(u16::from(RecordType::from(value)), value);
+            assert_eq!(u16::from(Class::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn parse_mnemonics() {
+        assert_eq!("mx".parse(), Ok(RecordType::MX));
+        assert_eq!("AAAA".parse(), Ok(RecordType::AAAA));
+        assert_eq!("TYPE28".parse(), Ok(RecordType::AAAA));
+        assert_eq!("TYPE65".parse(), Ok(RecordType::Unknown(65)));
+        assert!("BOGUS".parse::<RecordType>().is_err());
+
+        assert_eq!("chaos".parse(), Ok(Class::CH));
+        assert_eq!("CLASS1".parse(), Ok(Class::IN));
+        assert!("CLASS".parse::<Class>().is_err());
+    }
+
+    #[test]
+    fn display_mnemonics() {
+        assert_eq!(RecordType::CNAME.to_string(), "CNAME");
+        assert_eq!(RecordType::Unknown(65).to_string(), "TYPE65");
+        assert_eq!(Class::CH.to_string(), "CH");
+        assert_eq!(Class::Unknown(9).to_string(), "CLASS9");
+    }
+}
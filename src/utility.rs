@@ -0,0 +1,99 @@
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use crate::error::ParseError;
+
+/// Read a two-octet number in network byte order at `offset`
+pub fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, ParseError> {
+    let mut rdr = Cursor::new(bytes.get(offset..).unwrap_or_default());
+    rdr.read_u16::<BigEndian>()
+        .map_err(|_| ParseError::Truncated { offset })
+}
+
+/// Read a four-octet number in network byte order at `offset`
+pub fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ParseError> {
+    let mut rdr = Cursor::new(bytes.get(offset..).unwrap_or_default());
+    rdr.read_u32::<BigEndian>()
+        .map_err(|_| ParseError::Truncated { offset })
+}
+
+/// Format bytes as lowercase hex digits
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex digits, in either case, into bytes
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Extract the bits in `start..end` of a number, counting from the least
+/// significant bit
+pub fn get_bits_range(number: u16, start: u32, end: u32) -> u16 {
+    let range = end - start;
+    let mask = ((1u32 << range) - 1) as u16;
+
+    (number >> start) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_bit_range() {
+        let number = 0b11001100;
+        assert_eq!(get_bits_range(number, 4, 8), 0b1100);
+    }
+
+    #[test]
+    fn single_bit_range() {
+        assert_eq!(get_bits_range(0b1000_0000, 7, 8), 1);
+        assert_eq!(get_bits_range(0b0111_1111, 7, 8), 0);
+        assert_eq!(get_bits_range(0x8000, 15, 16), 1);
+    }
+
+    #[test]
+    fn full_range() {
+        assert_eq!(get_bits_range(0xABCD, 0, 16), 0xABCD);
+    }
+
+    #[test]
+    fn read_numbers() {
+        let bytes = [0x12, 0x34, 0x56, 0x78];
+        assert_eq!(read_u16(&bytes, 2), Ok(0x5678));
+        assert_eq!(read_u32(&bytes, 0), Ok(0x12345678));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(hex(&[0x0A, 0xBC]), "0abc");
+        assert_eq!(decode_hex("0aBC"), Some(vec![0x0A, 0xBC]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn read_past_end() {
+        let bytes = [0x12, 0x34, 0x56];
+        assert_eq!(
+            read_u16(&bytes, 2),
+            Err(ParseError::Truncated { offset: 2 })
+        );
+        assert_eq!(
+            read_u32(&bytes, 0),
+            Err(ParseError::Truncated { offset: 0 })
+        );
+        assert_eq!(
+            read_u16(&bytes, 8),
+            Err(ParseError::Truncated { offset: 8 })
+        );
+    }
+}
@@ -0,0 +1,1033 @@
+//! Parser for master files (RFC 1035 section 5), the text format zones are
+//! written in
+
+use std::fs;
+use std::iter::Peekable;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::{Chars, FromStr};
+
+use crate::error::{ZoneError, ZoneErrorKind};
+use crate::name::Name;
+use crate::rdata::RData;
+use crate::rr::ResourceRecord;
+use crate::types::{Class, RecordType};
+use crate::utility;
+
+/// Deepest nesting of `$INCLUDE` directives
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Longest character string
+const MAX_STRING_LENGTH: usize = 255;
+
+/// Most records a `$GENERATE` directive may expand into, as in BIND
+const MAX_GENERATE_RECORDS: usize = 65536;
+
+/// Widest `$GENERATE` value, as no label is longer
+const MAX_GENERATE_WIDTH: usize = 63;
+
+/// Parser turning master files into resource records
+#[derive(Debug, Clone)]
+pub struct ZoneParser {
+    /// Origin relative names are completed with, set by `$ORIGIN`
+    origin: Option<Name>,
+    /// TTL of records without one, set by `$TTL`
+    default_ttl: Option<u32>,
+    /// Owner of the previous record, used by records with a blank owner
+    last_owner: Option<Name>,
+    /// TTL of the previous record
+    last_ttl: Option<u32>,
+    /// Class of the previous record
+    last_class: Class,
+    /// File being parsed, if any, which `$INCLUDE` paths are relative to
+    file: Option<PathBuf>,
+    /// Number of `$INCLUDE` directives the current file is nested in
+    depth: usize,
+}
+
+/// Token of a master file
+#[derive(Debug, Clone)]
+struct Token {
+    /// Text of the token, with escapes kept and without quotes
+    text: String,
+    /// Whether the token is a quoted string
+    quoted: bool,
+    line: usize,
+    column: usize,
+}
+
+/// Entry of a master file, either a line or several lines joined by
+/// parentheses
+#[derive(Debug)]
+struct Entry {
+    tokens: Vec<Token>,
+    /// Whether the entry starts with a blank, meaning the owner is omitted
+    blank_owner: bool,
+    /// Position of the end of the entry
+    line: usize,
+    column: usize,
+}
+
+/// Reader of characters keeping track of their position
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+}
+
+/// Split a master file into entries, dropping comments
+fn tokenize(text: &str) -> Result<Vec<Entry>, (usize, usize, ZoneErrorKind)> {
+    let mut lexer = Lexer {
+        chars: text.chars().peekable(),
+        line: 1,
+        column: 1,
+    };
+    let mut entries = vec![];
+    let mut tokens = vec![];
+    let mut blank_owner = false;
+    let mut parenthesis = None;
+
+    loop {
+        let (line, column) = (lexer.line, lexer.column);
+        let Some(c) = lexer.peek() else {
+            break;
+        };
+        if column == 1 && tokens.is_empty() && parenthesis.is_none() {
+            blank_owner = c == ' ' || c == '\t';
+        }
+        match c {
+            '\n' => {
+                lexer.next();
+                if parenthesis.is_none() && !tokens.is_empty() {
+                    entries.push(Entry {
+                        tokens: std::mem::take(&mut tokens),
+                        blank_owner,
+                        line,
+                        column,
+                    });
+                }
+            }
+            ' ' | '\t' | '\r' => {
+                lexer.next();
+            }
+            ';' => {
+                while lexer.peek().is_some_and(|c| c != '\n') {
+                    lexer.next();
+                }
+            }
+            '(' => {
+                lexer.next();
+                if parenthesis.is_some() {
+                    return Err((line, column, ZoneErrorKind::UnbalancedParenthesis));
+                }
+                parenthesis = Some((line, column));
+            }
+            ')' => {
+                lexer.next();
+                if parenthesis.take().is_none() {
+                    return Err((line, column, ZoneErrorKind::UnbalancedParenthesis));
+                }
+            }
+            '"' => {
+                lexer.next();
+                let mut text = String::new();
+                loop {
+                    match lexer.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            text.push('\\');
+                            match lexer.next() {
+                                Some('\n') | None => {
+                                    return Err((line, column, ZoneErrorKind::UnterminatedString))
+                                }
+                                Some(c) => text.push(c),
+                            }
+                        }
+                        Some('\n') | None => {
+                            return Err((line, column, ZoneErrorKind::UnterminatedString))
+                        }
+                        Some(c) => text.push(c),
+                    }
+                }
+                tokens.push(Token {
+                    text,
+                    quoted: true,
+                    line,
+                    column,
+                });
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(c) = lexer.peek() {
+                    if matches!(c, ' ' | '\t' | '\r' | '\n' | ';' | '(' | ')' | '"') {
+                        break;
+                    }
+                    lexer.next();
+                    text.push(c);
+                    if c == '\\' {
+                        if let Some(c) = lexer.peek().filter(|&c| c != '\n') {
+                            lexer.next();
+                            text.push(c);
+                        }
+                    }
+                }
+                tokens.push(Token {
+                    text,
+                    quoted: false,
+                    line,
+                    column,
+                });
+            }
+        }
+    }
+
+    if let Some((line, column)) = parenthesis {
+        return Err((line, column, ZoneErrorKind::UnbalancedParenthesis));
+    }
+    if !tokens.is_empty() {
+        entries.push(Entry {
+            tokens,
+            blank_owner,
+            line: lexer.line,
+            column: lexer.column,
+        });
+    }
+    Ok(entries)
+}
+
+impl Default for ZoneParser {
+    fn default() -> Self {
+        ZoneParser::new(None)
+    }
+}
+
+impl ZoneParser {
+    /// Create a parser completing relative names with `origin` until a
+    /// `$ORIGIN` directive changes it
+    pub fn new(origin: Option<Name>) -> ZoneParser {
+        ZoneParser {
+            origin,
+            default_ttl: None,
+            last_owner: None,
+            last_ttl: None,
+            last_class: Class::IN,
+            file: None,
+            depth: 0,
+        }
+    }
+
+    /// Parse the records of a master file held in a string
+    pub fn parse_str(&mut self, text: &str) -> Result<Vec<ResourceRecord>, ZoneError> {
+        let entries =
+            tokenize(text).map_err(|(line, column, kind)| self.error(line, column, kind))?;
+        let mut records = vec![];
+        for entry in entries {
+            self.parse_entry(&entry, &mut records)?;
+        }
+        Ok(records)
+    }
+
+    /// Read and parse the records of a master file
+    pub fn parse_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<ResourceRecord>, ZoneError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|err| ZoneError {
+            file: Some(path.to_path_buf()),
+            line: None,
+            column: None,
+            kind: ZoneErrorKind::Unreadable(err.to_string()),
+        })?;
+        self.file = Some(path.to_path_buf());
+        self.parse_str(&text)
+    }
+
+    fn error(&self, line: usize, column: usize, kind: ZoneErrorKind) -> ZoneError {
+        ZoneError {
+            file: self.file.clone(),
+            line: Some(line),
+            column: Some(column),
+            kind,
+        }
+    }
+
+    fn token_error(&self, token: &Token, kind: ZoneErrorKind) -> ZoneError {
+        self.error(token.line, token.column, kind)
+    }
+
+    fn parse_entry(
+        &mut self,
+        entry: &Entry,
+        records: &mut Vec<ResourceRecord>,
+    ) -> Result<(), ZoneError> {
+        let first = &entry.tokens[0];
+        if entry.blank_owner || first.quoted || !first.text.starts_with('$') {
+            let rr = self.parse_record(&entry.tokens, entry.blank_owner, entry)?;
+            records.push(rr);
+            return Ok(());
+        }
+
+        let mut fields = Fields::new(self, &entry.tokens[1..], entry);
+        match first.text.to_ascii_uppercase().as_str() {
+            "$ORIGIN" => {
+                let origin = fields.name()?;
+                fields.end()?;
+                self.origin = Some(origin);
+            }
+            "$TTL" => {
+                let ttl = fields.ttl()?;
+                fields.end()?;
+                self.default_ttl = Some(ttl);
+            }
+            "$INCLUDE" => {
+                let token = fields.next()?;
+                let path = String::from_utf8_lossy(&unescape(&token.text)).into_owned();
+                let origin = fields.optional(|fields| fields.name())?;
+                fields.end()?;
+                self.include(token, Path::new(&path), origin, records)?;
+            }
+            "$GENERATE" => self.generate(&entry.tokens[1..], entry, records)?,
+            _ => {
+                return Err(
+                    self.token_error(first, ZoneErrorKind::UnknownDirective(first.text.clone()))
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the records of another file with the origin given to the
+    /// directive, leaving the state of this parser untouched
+    fn include(
+        &self,
+        token: &Token,
+        path: &Path,
+        origin: Option<Name>,
+        records: &mut Vec<ResourceRecord>,
+    ) -> Result<(), ZoneError> {
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(self.token_error(token, ZoneErrorKind::IncludeDepth));
+        }
+        let path = match self.file.as_ref().and_then(|file| file.parent()) {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+
+        let mut parser = ZoneParser {
+            origin: origin.or_else(|| self.origin.clone()),
+            file: Some(path.clone()),
+            depth: self.depth + 1,
+            ..self.clone()
+        };
+        let text = fs::read_to_string(&path).map_err(|err| {
+            self.token_error(
+                token,
+                ZoneErrorKind::Include {
+                    path: path.clone(),
+                    message: err.to_string(),
+                },
+            )
+        })?;
+        records.extend(parser.parse_str(&text)?);
+        Ok(())
+    }
+
+    /// Expand `$GENERATE range lhs [ttl] [class] type rhs` into one record
+    /// for each value of the range, replacing `$` in `lhs` and `rhs`
+    fn generate(
+        &mut self,
+        tokens: &[Token],
+        entry: &Entry,
+        records: &mut Vec<ResourceRecord>,
+    ) -> Result<(), ZoneError> {
+        let [range, lhs, middle @ .., rhs] = tokens else {
+            return Err(self.error(entry.line, entry.column, ZoneErrorKind::MissingField));
+        };
+        let invalid = |message: &str| {
+            self.token_error(range, ZoneErrorKind::InvalidGenerate(message.to_string()))
+        };
+        let (bounds, step) = range.text.split_once('/').unwrap_or((&range.text, "1"));
+        let (start, stop) = bounds
+            .split_once('-')
+            .ok_or_else(|| invalid("range must be start-stop"))?;
+        let (start, stop, step) = match (start.parse::<u32>(), stop.parse::<u32>(), step.parse()) {
+            (Ok(start), Ok(stop), Ok(step)) if start <= stop && step > 0 => (start, stop, step),
+            _ => return Err(invalid("invalid range")),
+        };
+        if (stop - start) as usize / step >= MAX_GENERATE_RECORDS {
+            return Err(invalid("range is too large"));
+        }
+
+        for i in (start..=stop).step_by(step) {
+            let substitute = |token: &Token| {
+                let text = substitute(&token.text, i).map_err(|message| {
+                    self.token_error(token, ZoneErrorKind::InvalidGenerate(message))
+                })?;
+                Ok(Token {
+                    text,
+                    ..token.clone()
+                })
+            };
+            let mut line = vec![substitute(lhs)?];
+            line.extend_from_slice(middle);
+            line.push(substitute(rhs)?);
+            let rr = self.parse_record(&line, false, entry)?;
+            records.push(rr);
+        }
+        Ok(())
+    }
+
+    fn parse_record(
+        &mut self,
+        tokens: &[Token],
+        blank_owner: bool,
+        entry: &Entry,
+    ) -> Result<ResourceRecord, ZoneError> {
+        let mut fields = Fields::new(self, tokens, entry);
+        let owner = if blank_owner {
+            self.last_owner
+                .clone()
+                .ok_or_else(|| fields.error_here(ZoneErrorKind::MissingOwner))?
+        } else {
+            fields.name()?
+        };
+
+        // TTL and class may come in either order, both being optional
+        let mut ttl = None;
+        let mut class = None;
+        for _ in 0..2 {
+            let Some(token) = fields.peek() else {
+                break;
+            };
+            if ttl.is_none() && !token.quoted {
+                if let Some(value) = parse_ttl(&token.text) {
+                    ttl = Some(value);
+                    fields.next()?;
+                    continue;
+                }
+            }
+            if class.is_none() && !token.quoted {
+                if let Ok(value) = Class::from_str(&token.text) {
+                    class = Some(value);
+                    fields.next()?;
+                    continue;
+                }
+            }
+            break;
+        }
+        let token = fields.next()?;
+        let an_type = RecordType::from_str(&token.text)
+            .ok()
+            .filter(|_| !token.quoted)
+            .ok_or_else(|| {
+                self.token_error(token, ZoneErrorKind::UnknownType(token.text.clone()))
+            })?;
+        let an_rdata = fields.rdata(an_type)?;
+        fields.end()?;
+
+        let an_ttl = match (ttl.or(self.default_ttl).or(self.last_ttl), &an_rdata) {
+            (Some(ttl), _) => ttl,
+            // Older zones rely on the SOA minimum as the default TTL
+            (None, RData::Soa { minimum, .. }) => *minimum,
+            (None, _) => return Err(self.token_error(&tokens[0], ZoneErrorKind::MissingTtl)),
+        };
+        let an_class = class.unwrap_or(self.last_class);
+
+        self.last_owner = Some(owner.clone());
+        self.last_ttl = Some(an_ttl);
+        self.last_class = an_class;
+        Ok(ResourceRecord {
+            an_name: owner,
+            an_type,
+            an_class,
+            an_ttl,
+            an_rdata,
+        })
+    }
+}
+
+/// Parse rdata of type `rr_type` given in presentation format, as found
+/// after the type of a record. Names must be absolute
+pub fn parse_rdata(text: &str, rr_type: RecordType) -> Result<RData, ZoneError> {
+    let parser = ZoneParser::default();
+    let entries =
+        tokenize(text).map_err(|(line, column, kind)| parser.error(line, column, kind))?;
+    let entry = match entries.as_slice() {
+        [] => Entry {
+            tokens: vec![],
+            blank_owner: false,
+            line: 1,
+            column: 1,
+        },
+        [_] => entries.into_iter().next().unwrap(),
+        [_, next, ..] => {
+            let token = &next.tokens[0];
+            return Err(parser.token_error(token, ZoneErrorKind::TrailingText(token.text.clone())));
+        }
+    };
+
+    let mut fields = Fields::new(&parser, &entry.tokens, &entry);
+    let rdata = fields.rdata(rr_type)?;
+    fields.end()?;
+    Ok(rdata)
+}
+
+/// Cursor over the fields of an entry
+struct Fields<'a> {
+    parser: &'a ZoneParser,
+    tokens: &'a [Token],
+    pos: usize,
+    entry: &'a Entry,
+}
+
+impl<'a> Fields<'a> {
+    fn new(parser: &'a ZoneParser, tokens: &'a [Token], entry: &'a Entry) -> Fields<'a> {
+        Fields {
+            parser,
+            tokens,
+            pos: 0,
+            entry,
+        }
+    }
+
+    /// Error at the end of the entry
+    fn error_here(&self, kind: ZoneErrorKind) -> ZoneError {
+        match self.tokens.get(self.pos) {
+            Some(token) => self.parser.token_error(token, kind),
+            None => self.parser.error(self.entry.line, self.entry.column, kind),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<&'a Token, ZoneError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| self.error_here(ZoneErrorKind::MissingField))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Read a field only if the entry goes on
+    fn optional<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, ZoneError>,
+    ) -> Result<Option<T>, ZoneError> {
+        match self.peek() {
+            Some(_) => read(self).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn end(&self) -> Result<(), ZoneError> {
+        match self.peek() {
+            Some(token) => Err(self
+                .parser
+                .token_error(token, ZoneErrorKind::TrailingText(token.text.clone()))),
+            None => Ok(()),
+        }
+    }
+
+    /// Read a domain name, completing relative names with the origin
+    fn name(&mut self) -> Result<Name, ZoneError> {
+        let token = self.next()?;
+        let origin = || {
+            self.parser
+                .origin
+                .clone()
+                .ok_or_else(|| self.parser.token_error(token, ZoneErrorKind::MissingOrigin))
+        };
+        if token.text == "@" && !token.quoted {
+            return origin();
+        }
+        let name = Name::from_str(&token.text).map_err(|err| {
+            self.parser
+                .token_error(token, ZoneErrorKind::InvalidName(err))
+        })?;
+        if is_absolute(&token.text) {
+            return Ok(name);
+        }
+        name.join(&origin()?).map_err(|err| {
+            self.parser
+                .token_error(token, ZoneErrorKind::InvalidName(err))
+        })
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, ZoneError> {
+        let token = self.next()?;
+        token.text.parse().map_err(|_| {
+            self.parser
+                .token_error(token, ZoneErrorKind::InvalidValue(token.text.clone()))
+        })
+    }
+
+    fn ttl(&mut self) -> Result<u32, ZoneError> {
+        let token = self.next()?;
+        parse_ttl(&token.text).ok_or_else(|| {
+            self.parser
+                .token_error(token, ZoneErrorKind::InvalidValue(token.text.clone()))
+        })
+    }
+
+    /// Read a character string, quoted or not
+    fn string(&mut self) -> Result<Vec<u8>, ZoneError> {
+        let token = self.next()?;
+        let string = unescape(&token.text);
+        if string.len() > MAX_STRING_LENGTH {
+            return Err(self.parser.token_error(token, ZoneErrorKind::InvalidString));
+        }
+        Ok(string)
+    }
+
+    /// Read the rdata of a record of type `rr_type`
+    fn rdata(&mut self, rr_type: RecordType) -> Result<RData, ZoneError> {
+        if let Some(token) = self
+            .peek()
+            .filter(|token| token.text == "\\#" && !token.quoted)
+        {
+            self.pos += 1;
+            return self.generic_rdata(token, rr_type);
+        }
+
+        let rdata = match rr_type {
+            RecordType::A => RData::A(self.number::<Ipv4Addr>()?),
+            RecordType::AAAA => RData::Aaaa(self.number::<Ipv6Addr>()?),
+            RecordType::NS => RData::Ns(self.name()?),
+            RecordType::CNAME => RData::Cname(self.name()?),
+            RecordType::PTR => RData::Ptr(self.name()?),
+            RecordType::SOA => RData::Soa {
+                mname: self.name()?,
+                rname: self.name()?,
+                serial: self.number()?,
+                refresh: self.ttl()?,
+                retry: self.ttl()?,
+                expire: self.ttl()?,
+                minimum: self.ttl()?,
+            },
+            RecordType::MX => RData::Mx {
+                preference: self.number()?,
+                exchange: self.name()?,
+            },
+            RecordType::TXT => {
+                let mut strings = vec![];
+                while self.peek().is_some() {
+                    strings.push(self.string()?);
+                }
+                RData::Txt(strings)
+            }
+            RecordType::SRV => RData::Srv {
+                priority: self.number()?,
+                weight: self.number()?,
+                port: self.number()?,
+                target: self.name()?,
+            },
+            RecordType::CAA => RData::Caa {
+                flags: self.number()?,
+                tag: self.string()?,
+                value: unescape(&self.next()?.text),
+            },
+            RecordType::HINFO => RData::Hinfo {
+                cpu: self.string()?,
+                os: self.string()?,
+            },
+            _ => return Err(self.error_here(ZoneErrorKind::UnsupportedType(rr_type))),
+        };
+        Ok(rdata)
+    }
+
+    /// Read rdata in the generic `\# length hex` form (RFC 3597)
+    fn generic_rdata(&mut self, token: &Token, rr_type: RecordType) -> Result<RData, ZoneError> {
+        let invalid = |message: String| {
+            self.parser
+                .token_error(token, ZoneErrorKind::InvalidRData(message))
+        };
+        let length = self.number::<u16>()? as usize;
+        let mut hex = String::new();
+        while let Some(token) = self.peek() {
+            hex.push_str(&token.text);
+            self.pos += 1;
+        }
+
+        let data =
+            utility::decode_hex(&hex).ok_or_else(|| invalid(format!("invalid hex {}", hex)))?;
+        if data.len() != length {
+            return Err(invalid(format!(
+                "length {} does not match {} bytes of data",
+                length,
+                data.len()
+            )));
+        }
+        RData::parse(&data, 0, length, rr_type).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+/// Whether a name in presentation format ends with a dot that is not
+/// escaped
+fn is_absolute(text: &str) -> bool {
+    let Some(rest) = text.strip_suffix('.') else {
+        return false;
+    };
+    let backslashes = rest.bytes().rev().take_while(|&b| b == b'\\').count();
+    backslashes.is_multiple_of(2)
+}
+
+/// Parse a TTL given in seconds, or with units as in `1h30m` or `2W`
+fn parse_ttl(text: &str) -> Option<u32> {
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().ok();
+    }
+
+    let mut total = 0u32;
+    let mut value = None::<u32>;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = Some(value.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 7 * 24 * 3600,
+            'd' => 24 * 3600,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(value.take()?.checked_mul(unit)?)?;
+    }
+    match value {
+        Some(_) => None,
+        None => Some(total),
+    }
+}
+
+/// Replace the escapes of a character string, `\X` by `X` and `\DDD` by the
+/// byte of that decimal value
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut string = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            string.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let value = bytes
+            .get(i + 1..i + 4)
+            .filter(|digits| digits.iter().all(u8::is_ascii_digit))
+            .and_then(|digits| std::str::from_utf8(digits).ok()?.parse::<u8>().ok());
+        match value {
+            Some(value) => {
+                string.push(value);
+                i += 4;
+            }
+            None => {
+                string.push(bytes[i + 1]);
+                i += 2;
+            }
+        }
+    }
+    string
+}
+
+/// Replace `$` in a `$GENERATE` template by `value`. `${offset,width,base}`
+/// adds an offset and pads the value to a width in decimal (`d`), octal
+/// (`o`) or hexadecimal (`x`, `X`), and `\$` stands for a dollar sign
+fn substitute(template: &str, value: u32) -> Result<String, String> {
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                text.push(c);
+                if let Some(c) = chars.next() {
+                    text.push(c);
+                }
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let modifier = chars.by_ref().take_while(|&c| c != '}').collect::<String>();
+                let mut parts = modifier.split(',');
+                let offset = parts.next().unwrap_or("0").parse::<i64>();
+                let width = parts.next().unwrap_or("0").parse::<usize>();
+                let base = parts.next().unwrap_or("d");
+                let (Ok(offset), Ok(width), None) = (offset, width, parts.next()) else {
+                    return Err(format!("invalid modifier {{{}}}", modifier));
+                };
+                if width > MAX_GENERATE_WIDTH {
+                    return Err(format!("width {} is too large", width));
+                }
+                let value = u32::try_from(value as i64 + offset)
+                    .map_err(|_| format!("offset {} is out of range", offset))?;
+                let formatted = match base {
+                    "d" => format!("{:0width$}", value),
+                    "o" => format!("{:0width$o}", value),
+                    "x" => format!("{:0width$x}", value),
+                    "X" => format!("{:0width$X}", value),
+                    _ => return Err(format!("unsupported base {}", base)),
+                };
+                text.push_str(&formatted);
+            }
+            '$' => text.push_str(&value.to_string()),
+            c => text.push(c),
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies;
+    use proptest::prelude::*;
+
+    fn name(text: &str) -> Name {
+        text.parse().unwrap()
+    }
+
+    fn parse(text: &str) -> Result<Vec<ResourceRecord>, ZoneError> {
+        ZoneParser::new(Some(name("example.com"))).parse_str(text)
+    }
+
+    const ZONE: &str = r#"
+$TTL 1h ; default TTL
+@   IN  SOA ns1 hostmaster (
+            2024010101 ; serial
+            2h 1h 2W 300 )
+    IN  NS  ns1
+    IN  MX  10 mail.example.net.
+ns1 300 A   192.0.2.1
+        AAAA 2001:db8::1
+www CNAME @
+txt TXT "v=spf1 -all" plain "quote \" and \\" \255
+_sip._tcp SRV 1 2 5060 sip
+@ CAA 128 issue "letsencrypt.org"
+host HINFO "x86" Linux
+$ORIGIN sub.example.com.
+a\.b TYPE65280 \# 4 0A00 0001
+c A \# 4 C0000202
+"#;
+
+    #[test]
+    fn parse_zone() {
+        let records = parse(ZONE).unwrap();
+        let text = records
+            .iter()
+            .map(|rr| rr.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let expected = "\
+example.com.\t3600\tIN\tSOA\tns1.example.com. hostmaster.example.com. 2024010101 7200 3600 1209600 300
+example.com.\t3600\tIN\tNS\tns1.example.com.
+example.com.\t3600\tIN\tMX\t10 mail.example.net.
+ns1.example.com.\t300\tIN\tA\t192.0.2.1
+ns1.example.com.\t3600\tIN\tAAAA\t2001:db8::1
+www.example.com.\t3600\tIN\tCNAME\texample.com.
+txt.example.com.\t3600\tIN\tTXT\t\"v=spf1 -all\" \"plain\" \"quote \\\" and \\\\\" \"\\255\"
+_sip._tcp.example.com.\t3600\tIN\tSRV\t1 2 5060 sip.example.com.
+example.com.\t3600\tIN\tCAA\t128 issue \"letsencrypt.org\"
+host.example.com.\t3600\tIN\tHINFO\t\"x86\" \"Linux\"
+a\\.b.sub.example.com.\t3600\tIN\tTYPE65280\t\\# 4 0a000001
+c.sub.example.com.\t3600\tIN\tA\t192.0.2.2";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn take_ttl_from_previous_record_or_soa() {
+        let records = parse("@ SOA ns1 host 1 2 3 4 60\na 120 A 192.0.2.1\nb A 192.0.2.2").unwrap();
+        let ttls = records.iter().map(|rr| rr.an_ttl).collect::<Vec<_>>();
+        assert_eq!(ttls, [60, 120, 120]);
+    }
+
+    #[test]
+    fn generate_records() {
+        let records = parse(
+            "$TTL 60\n$GENERATE 1-9/4 host-$ A 10.0.0.$\n$GENERATE 10-11 ${-9,3,x} PTR host-${0,0,X}\\$",
+        )
+        .unwrap();
+        let text = records.iter().map(|rr| rr.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            [
+                "host-1.example.com.\t60\tIN\tA\t10.0.0.1",
+                "host-5.example.com.\t60\tIN\tA\t10.0.0.5",
+                "host-9.example.com.\t60\tIN\tA\t10.0.0.9",
+                "001.example.com.\t60\tIN\tPTR\thost-A\\$.example.com.",
+                "002.example.com.\t60\tIN\tPTR\thost-B\\$.example.com.",
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_oversized_generate() {
+        for text in [
+            "$GENERATE 0-4294967295 host-$ A 10.0.0.1",
+            "$GENERATE 0-65536 host-$ A 10.0.0.1",
+            "$GENERATE 1-2 host-${0,64,d} A 10.0.0.1",
+            "$GENERATE 1-2 host-${0,18446744073709551615,d} A 10.0.0.1",
+        ] {
+            let err = parse(text).unwrap_err();
+            assert!(
+                matches!(err.kind, ZoneErrorKind::InvalidGenerate(_)),
+                "{}: {:?}",
+                text,
+                err
+            );
+        }
+        assert_eq!(
+            parse("$TTL 60\n$GENERATE 0-131070/2 host-$ A 10.0.0.1")
+                .unwrap()
+                .len(),
+            65536
+        );
+    }
+
+    #[test]
+    fn include_files() {
+        let dir = std::env::temp_dir().join(format!("zone-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.zone"),
+            "$TTL 60\nwww A 192.0.2.1\n$INCLUDE hosts.zone sub\nmail A 192.0.2.3\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("hosts.zone"),
+            "$ORIGIN example.org.\nhost A 192.0.2.2\n",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.zone"), "$TTL 60\n\nbad A 192.0.2\n").unwrap();
+
+        let records = ZoneParser::new(Some(name("example.com")))
+            .parse_file(dir.join("main.zone"))
+            .unwrap();
+        let owners = records
+            .iter()
+            .map(|rr| rr.an_name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            owners,
+            ["www.example.com.", "host.example.org.", "mail.example.com."]
+        );
+
+        let err = ZoneParser::new(Some(name("example.com")))
+            .parse_str(&format!("$INCLUDE {}", dir.join("broken.zone").display()))
+            .unwrap_err();
+        assert_eq!(err.file, Some(dir.join("broken.zone")));
+        assert_eq!((err.line, err.column), (Some(3), Some(7)));
+        assert!(err
+            .to_string()
+            .ends_with("broken.zone:3:7: invalid value 192.0.2"));
+
+        let err = parse("$INCLUDE missing.zone").unwrap_err();
+        assert!(matches!(err.kind, ZoneErrorKind::Include { .. }));
+
+        let missing = dir.join("missing.zone");
+        let err = ZoneParser::default().parse_file(&missing).unwrap_err();
+        assert_eq!(err.file, Some(missing.clone()));
+        assert_eq!((err.line, err.column), (None, None));
+        assert!(matches!(err.kind, ZoneErrorKind::Unreadable(_)));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{}: can't read file: ", missing.display())));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn report_error_positions() {
+        let error = |text: &str| {
+            let err = parse(text).unwrap_err();
+            (err.line.unwrap(), err.column.unwrap(), err.kind)
+        };
+        assert_eq!(
+            error("$TTL 60\nwww  BOGUS 1"),
+            (2, 6, ZoneErrorKind::UnknownType("BOGUS".to_string()))
+        );
+        assert_eq!(
+            error("$TTL 60\n@ SOA ns1 host (\n1 2 3 4"),
+            (2, 16, ZoneErrorKind::UnbalancedParenthesis)
+        );
+        assert_eq!(
+            error("$TTL 60\nwww A 192.0.2.1 )"),
+            (2, 17, ZoneErrorKind::UnbalancedParenthesis)
+        );
+        assert_eq!(
+            error("www 60 TXT \"open\n"),
+            (1, 12, ZoneErrorKind::UnterminatedString)
+        );
+        assert_eq!(error("www 60 MX 10"), (1, 13, ZoneErrorKind::MissingField));
+        assert_eq!(
+            error("www 60 A 192.0.2.1 extra"),
+            (1, 20, ZoneErrorKind::TrailingText("extra".to_string()))
+        );
+        assert_eq!(
+            error(" 60 A 192.0.2.1"),
+            (1, 2, ZoneErrorKind::MissingOwner)
+        );
+        assert_eq!(error("www A 192.0.2.1"), (1, 1, ZoneErrorKind::MissingTtl));
+        assert_eq!(
+            error("www 60 DS 1 2 3 abcd"),
+            (1, 11, ZoneErrorKind::UnsupportedType(RecordType::DS))
+        );
+        assert_eq!(
+            error("$FOO bar"),
+            (1, 1, ZoneErrorKind::UnknownDirective("$FOO".to_string()))
+        );
+        let err = ZoneParser::default()
+            .parse_str("www 60 A 192.0.2.1")
+            .unwrap_err();
+        assert_eq!(err.kind, ZoneErrorKind::MissingOrigin);
+    }
+
+    #[test]
+    fn parse_ttl_units() {
+        assert_eq!(parse_ttl("3600"), Some(3600));
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1W2d"), Some(9 * 86400));
+        assert_eq!(parse_ttl("1h30"), None);
+        assert_eq!(parse_ttl("h"), None);
+        assert_eq!(parse_ttl("IN"), None);
+    }
+
+    #[test]
+    fn parse_rdata_alone() {
+        assert_eq!(
+            parse_rdata("10 mail.example.com.", RecordType::MX),
+            Ok(RData::Mx {
+                preference: 10,
+                exchange: name("mail.example.com")
+            })
+        );
+        assert_eq!(parse_rdata("", RecordType::TXT), Ok(RData::Txt(vec![])));
+        let err = parse_rdata("10 mail", RecordType::MX).unwrap_err();
+        assert_eq!(err.kind, ZoneErrorKind::MissingOrigin);
+        let err = parse_rdata("192.0.2.1\n192.0.2.2", RecordType::A).unwrap_err();
+        assert_eq!((err.line, err.column), (Some(2), Some(1)));
+    }
+
+    proptest! {
+        #[test]
+        fn presentation_round_trip(rr in strategies::resource_record()) {
+            let records = ZoneParser::default().parse_str(&rr.to_string());
+            prop_assert_eq!(records, Ok(vec![rr]));
+        }
+    }
+}